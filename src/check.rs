@@ -0,0 +1,365 @@
+use std::collections::HashMap;
+use std::fs;
+
+use crate::runtime::error::RuntimeError;
+use crate::runtime::lyric::{Lyric, StrictLine};
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+const CHECK_USAGE: &str = "Usage: ciryl check [OPTIONS] FILE...
+
+Check that each lyric FILE is a well formed LRC file and report the
+problems found, line by line. Exit with an error if any FILE has errors.
+
+OPTIONS
+  -w, --deny-warnings Fail on warnings too.
+  -h, --help          Print this message.";
+
+const KNOWN_TAGS: [&str; 11] = [
+    "ar", "al", "ti", "au", "lr", "length", "by", "offset", "re", "tool", "ve",
+];
+
+#[derive(Debug, PartialEq)]
+enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, PartialEq)]
+struct Diagnostic {
+    line: usize,
+    severity: Severity,
+    message: String,
+}
+
+struct Checker {
+    diagnostics: Vec<Diagnostic>,
+    length: Option<usize>,
+    first_use: HashMap<usize, usize>,
+    previous: Option<usize>,
+}
+
+impl Checker {
+    fn new() -> Checker {
+        Checker {
+            diagnostics: Vec::new(),
+            length: None,
+            first_use: HashMap::new(),
+            previous: None,
+        }
+    }
+
+    fn error(&mut self, line: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            severity: Severity::Error,
+            message,
+        });
+    }
+
+    fn warning(&mut self, line: usize, message: String) {
+        self.diagnostics.push(Diagnostic {
+            line,
+            severity: Severity::Warning,
+            message,
+        });
+    }
+
+    /// Whether the timestamp is exactly "mm:ss.xx", as most players expect.
+    fn is_standard_timestamp(timestamp: &str) -> bool {
+        let bytes = timestamp.as_bytes();
+        bytes.len() == 8
+            && bytes[2] == b':'
+            && bytes[5] == b'.'
+            && [0, 1, 3, 4, 6, 7]
+                .iter()
+                .all(|i| bytes[*i].is_ascii_digit())
+            && bytes[3] < b'6'
+    }
+
+    fn check_tag(&mut self, line: usize, key: &str, value: &str) {
+        match key.to_ascii_lowercase().as_str() {
+            "length" => match Lyric::parse_timestamp(&format!("{}.00", value)) {
+                Ok(length) => self.length = Some(length),
+                Err(_) => self.error(line, format!("malformed length '{}'", value)),
+            },
+            "offset" => {
+                if value.parse::<i64>().is_err() {
+                    self.error(line, format!("malformed offset '{}'", value));
+                }
+            }
+            tag if KNOWN_TAGS.contains(&tag) => {}
+            _ => self.warning(line, format!("unknown tag '[{}:]'", key)),
+        }
+    }
+
+    fn check_timestamps(&mut self, line: usize, parsed: &StrictLine) {
+        let mut timestamps: Vec<usize> = Vec::new();
+
+        for (group, timestamp) in &parsed.timestamps {
+            match timestamp {
+                Ok(timestamp) => {
+                    if !Checker::is_standard_timestamp(group) {
+                        self.warning(line, format!("non-standard timestamp '[{}]'", group));
+                    }
+                    timestamps.push(*timestamp);
+                }
+                Err(_) => self.error(
+                    line,
+                    format!("malformed timestamp '[{}]', the verse is dropped", group),
+                ),
+            }
+        }
+
+        for timestamp in &timestamps {
+            match self.first_use.get(timestamp) {
+                Some(first) => {
+                    let first = *first;
+                    self.error(
+                        line,
+                        format!(
+                            "duplicate timestamp '[{}]', first used on line {}",
+                            Lyric::format_timestamp(*timestamp),
+                            first
+                        ),
+                    );
+                }
+                None => {
+                    self.first_use.insert(*timestamp, line);
+                }
+            }
+
+            if let Some(length) = self.length
+                && *timestamp > length
+            {
+                self.error(
+                    line,
+                    format!(
+                        "timestamp '[{}]' is beyond the song length '{}'",
+                        Lyric::format_timestamp(*timestamp),
+                        Lyric::format_timestamp(length)
+                    ),
+                );
+            }
+        }
+
+        /* Lines with many timestamps repeat a verse, they can't be ordered */
+        if let [timestamp] = timestamps[..] {
+            if let Some(previous) = self.previous
+                && timestamp < previous
+            {
+                self.warning(
+                    line,
+                    "timestamp earlier than the previous verse".to_string(),
+                );
+            }
+            self.previous = Some(timestamp);
+        }
+
+        for text in &parsed.ignored {
+            self.warning(
+                line,
+                format!("text '{}' before a timestamp is ignored", text),
+            );
+        }
+
+        if !timestamps.is_empty() && parsed.text.is_empty() {
+            self.warning(line, "timestamp without text".to_string());
+        }
+    }
+
+    fn check_line(&mut self, line: usize, bytes: &[u8]) {
+        if std::str::from_utf8(bytes).is_err() {
+            self.error(line, "invalid UTF-8 encoding".to_string());
+        }
+
+        let content = String::from_utf8_lossy(bytes);
+        let mut content = content.trim();
+
+        if line == 1 {
+            content = content.trim_start_matches('\u{feff}');
+        } else if content.contains('\u{feff}') {
+            self.warning(
+                line,
+                "byte order mark in the middle of the file".to_string(),
+            );
+        }
+
+        if content.is_empty() {
+            return;
+        }
+
+        if let Some((key, value)) = Lyric::parse_line_tag(content) {
+            self.check_tag(line, key, value);
+            return;
+        }
+
+        let parsed = Lyric::parse_line_strict(content);
+        if parsed.unclosed {
+            self.error(line, "unclosed '['".to_string());
+        } else if parsed.timestamps.is_empty() {
+            self.warning(line, "line without timestamp is ignored".to_string());
+        } else {
+            self.check_timestamps(line, &parsed);
+        }
+    }
+
+    fn check(content: &[u8]) -> Vec<Diagnostic> {
+        let mut checker = Checker::new();
+
+        for (index, line) in content.split(|byte| *byte == b'\n').enumerate() {
+            checker.check_line(index + 1, line);
+        }
+
+        checker.diagnostics
+    }
+}
+
+pub fn check(args: &[String]) -> Result<()> {
+    let mut deny_warnings = false;
+    let mut files: Vec<&String> = Vec::new();
+
+    for arg in args {
+        match arg.as_str() {
+            "-w" | "--deny-warnings" => deny_warnings = true,
+            "-h" | "--help" => {
+                println!("{}", CHECK_USAGE);
+                return Ok(());
+            }
+            option if option.starts_with('-') => {
+                return Err(RuntimeError::UsageError(format!(
+                    "unknown option '{}'",
+                    option
+                )));
+            }
+            _ => files.push(arg),
+        }
+    }
+
+    if files.is_empty() {
+        return Err(RuntimeError::UsageError(
+            "FILE argument is required".to_string(),
+        ));
+    }
+
+    let mut failed = 0;
+    for file in files {
+        let diagnostics = match fs::read(file) {
+            Ok(content) => Checker::check(&content),
+            Err(error) => {
+                println!("{}: error: {}", file, error);
+                failed += 1;
+                continue;
+            }
+        };
+
+        for diagnostic in &diagnostics {
+            let severity = match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            println!(
+                "{}:{}: {}: {}",
+                file, diagnostic.line, severity, diagnostic.message
+            );
+        }
+
+        if diagnostics
+            .iter()
+            .any(|d| deny_warnings || d.severity == Severity::Error)
+        {
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        return Err(RuntimeError::CheckFailed(failed));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::check::{Checker, Diagnostic, Severity};
+
+    fn messages(content: impl AsRef<[u8]>) -> Vec<(usize, Severity)> {
+        Checker::check(content.as_ref())
+            .into_iter()
+            .map(|Diagnostic { line, severity, .. }| (line, severity))
+            .collect()
+    }
+
+    #[test]
+    fn well_formed_lyric() {
+        let content = "\u{feff}[ar:Sleep Token]\r\n[ti:Granite]\n[length:03:34]\n\n\
+                       [00:01.00] First verse\n[00:05.50][01:05.50] Chorus\n[00:10.00] Last verse\n";
+        assert!(Checker::check(content.as_bytes()).is_empty());
+    }
+
+    #[test]
+    fn malformed_timestamps() {
+        let content = "[00:01.00] ok\n[00:3.00] short\n[00:04] no fraction\n[00:05.00 open";
+        assert_eq!(
+            messages(content),
+            vec![
+                (2, Severity::Error),
+                (3, Severity::Error),
+                (4, Severity::Error)
+            ]
+        );
+    }
+
+    #[test]
+    fn timestamps_order_and_duplicates() {
+        let content = "[00:05.00] second\n[00:01.00] first\n[00:05.00] again";
+        assert_eq!(
+            messages(content),
+            vec![(2, Severity::Warning), (3, Severity::Error)]
+        );
+    }
+
+    #[test]
+    fn timestamps_beyond_length() {
+        let content = "[length:01:00]\n[00:59.99] in\n[01:00.01] out";
+        assert_eq!(messages(content), vec![(3, Severity::Error)]);
+    }
+
+    #[test]
+    fn tags_text_and_encoding() {
+        let content = b"[xx:unknown]\n[offset:abc]\n[00:01.00]\nplain text\n[00:02.00] \xff";
+        assert_eq!(
+            messages(content),
+            vec![
+                (1, Severity::Warning),
+                (2, Severity::Error),
+                (3, Severity::Warning),
+                (4, Severity::Warning),
+                (5, Severity::Error),
+            ]
+        );
+    }
+
+    #[test]
+    fn read_as_the_lyric_parser() {
+        /* The trailing group is a second timestamp of the verse, left empty */
+        let content = "[00:01.00] text [00:02.00]";
+        assert_eq!(
+            messages(content),
+            vec![(1, Severity::Warning), (1, Severity::Warning)]
+        );
+        assert_eq!(
+            Checker::check(content.as_bytes())[0].message,
+            "text 'text' before a timestamp is ignored"
+        );
+    }
+
+    #[test]
+    fn non_standard_timestamp() {
+        assert!(Checker::is_standard_timestamp("01:59.99"));
+        assert!(!Checker::is_standard_timestamp("01:60.00"));
+        assert!(!Checker::is_standard_timestamp("01:59.999"));
+        assert!(!Checker::is_standard_timestamp("01-59.99"));
+    }
+}
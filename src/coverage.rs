@@ -0,0 +1,262 @@
+use serde_json::json;
+use std::fs;
+use unicode_width::UnicodeWidthStr;
+
+use crate::runtime::cmus::{Cmus, LibraryTrack, PlayerSongInfo};
+use crate::runtime::error::RuntimeError;
+use crate::runtime::lyric::Lyric;
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+const COVERAGE_USAGE: &str = "Usage: ciryl coverage [OPTIONS]
+
+Report which tracks of the cmus library have a lyric in $LYRICS_DIR.
+
+OPTIONS
+  -j, --json          Print the report as JSON.
+  -m, --missing       List only the tracks without a synced lyric.
+  -h, --help          Print this message.";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Coverage {
+    Synced,
+    Unsynced,
+    Missing,
+    /// No artist nor title to name the lyric after, and no sidecar lyric.
+    Untagged,
+}
+
+impl Coverage {
+    const ALL: [Coverage; 4] = [
+        Coverage::Synced,
+        Coverage::Unsynced,
+        Coverage::Missing,
+        Coverage::Untagged,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            Coverage::Synced => "synced",
+            Coverage::Unsynced => "unsynced",
+            Coverage::Missing => "missing",
+            Coverage::Untagged => "untagged",
+        }
+    }
+}
+
+struct TrackCoverage {
+    file: String,
+    artist: String,
+    title: String,
+    coverage: Coverage,
+}
+
+fn track_coverage(song: &PlayerSongInfo, lyrics_dirs: &[String]) -> Coverage {
+    /* Without tags the digest names no lyric, only the sidecar can match */
+    let untagged = song.artist.is_empty() && song.title.is_empty();
+    let paths = match untagged {
+        true => Lyric::sidecar(song).into_iter().collect(),
+        false => Lyric::paths_in(lyrics_dirs, song),
+    };
+
+    match paths.iter().find_map(|path| fs::read(path).ok()) {
+        Some(content) if Lyric::is_synced(&String::from_utf8_lossy(&content)) => Coverage::Synced,
+        Some(_) => Coverage::Unsynced,
+        None if untagged => Coverage::Untagged,
+        None => Coverage::Missing,
+    }
+}
+
+fn library_coverage(library: Vec<LibraryTrack>, lyrics_dirs: &[String]) -> Vec<TrackCoverage> {
+    library
+        .into_iter()
+        .map(|track| {
+            let song = PlayerSongInfo {
+                title: track.title,
+                artist: track.artist,
+                file: track.file,
+                ..PlayerSongInfo::new()
+            };
+
+            TrackCoverage {
+                coverage: track_coverage(&song, lyrics_dirs),
+                file: song.file,
+                artist: song.artist,
+                title: song.title,
+            }
+        })
+        .collect()
+}
+
+/// Lines of the report as a table, artists padded to the widest one.
+fn table(report: &[&TrackCoverage]) -> Vec<String> {
+    let artist_width = report
+        .iter()
+        .map(|track| track.artist.width())
+        .max()
+        .unwrap_or(0)
+        .max("ARTIST".len());
+    let pad = |text: &str| format!("{}{}", text, " ".repeat(artist_width - text.width()));
+
+    let mut lines = vec![format!("{:<8}  {}  TITLE", "STATUS", pad("ARTIST"))];
+    lines.extend(report.iter().map(|track| {
+        format!(
+            "{:<8}  {}  {}",
+            track.coverage.name(),
+            pad(&track.artist),
+            track.title
+        )
+    }));
+
+    lines
+}
+
+fn json(report: &[&TrackCoverage], count: impl Fn(Coverage) -> usize) -> serde_json::Value {
+    let tracks: Vec<serde_json::Value> = report
+        .iter()
+        .map(|track| {
+            json!({
+                "file": track.file,
+                "artist": track.artist,
+                "title": track.title,
+                "status": track.coverage.name(),
+            })
+        })
+        .collect();
+
+    json!({
+        "total": Coverage::ALL.into_iter().map(&count).sum::<usize>(),
+        "synced": count(Coverage::Synced),
+        "unsynced": count(Coverage::Unsynced),
+        "missing": count(Coverage::Missing),
+        "untagged": count(Coverage::Untagged),
+        "tracks": tracks,
+    })
+}
+
+pub fn coverage(args: &[String]) -> Result<()> {
+    let mut as_json = false;
+    let mut only_missing = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "-j" | "--json" => as_json = true,
+            "-m" | "--missing" => only_missing = true,
+            "-h" | "--help" => {
+                println!("{}", COVERAGE_USAGE);
+                return Ok(());
+            }
+            option => {
+                return Err(RuntimeError::UsageError(format!(
+                    "unknown option '{}'",
+                    option
+                )));
+            }
+        }
+    }
+
+    let library = Cmus::new()?
+        .library()
+        .map_err(|error| RuntimeError::PlayerError(error.to_string()))?;
+    let report = library_coverage(library, &Lyric::lyrics_dirs()?);
+    let count = |coverage: Coverage| {
+        report
+            .iter()
+            .filter(|track| track.coverage == coverage)
+            .count()
+    };
+
+    let listed: Vec<&TrackCoverage> = report
+        .iter()
+        .filter(|track| !only_missing || track.coverage != Coverage::Synced)
+        .collect();
+
+    if as_json {
+        println!("{}", json(&listed, count));
+        return Ok(());
+    }
+
+    for line in table(&listed) {
+        println!("{}", line);
+    }
+
+    let total = report.len().max(1);
+    println!(
+        "\n{} tracks: {} synced ({}%), {} unsynced, {} missing, {} untagged",
+        report.len(),
+        count(Coverage::Synced),
+        count(Coverage::Synced) * 100 / total,
+        count(Coverage::Unsynced),
+        count(Coverage::Missing),
+        count(Coverage::Untagged),
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::coverage::{Coverage, TrackCoverage, json, library_coverage, table};
+    use crate::runtime::cmus::LibraryTrack;
+    use crate::runtime::lyric::Lyric;
+    use std::fs;
+
+    #[test]
+    fn classify_library() {
+        let dir = std::env::temp_dir().join(format!("ciryl-coverage-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let lyric =
+            |artist: &str, title: &str| dir.join(format!("{}.lrc", Lyric::digest(artist, title)));
+        fs::write(lyric("Linkin Park", "Numb"), "[00:01.00] numb").unwrap();
+        fs::write(lyric("Linkin Park", "Faint"), "faint").unwrap();
+        /* What an untagged track would wrongly be matched with */
+        fs::write(lyric("", ""), "[00:01.00] nobody").unwrap();
+
+        let track = |artist: &str, title: &str| LibraryTrack {
+            file: format!("/music/{} - {}.flac", artist, title),
+            artist: artist.to_string(),
+            title: title.to_string(),
+        };
+        let library = vec![
+            track("Linkin Park", "Numb"),
+            track("Linkin Park", "Faint"),
+            track("宇多田ヒカル", "First Love"),
+            track("", ""),
+        ];
+        let report = library_coverage(library, &[dir.to_string_lossy().to_string()]);
+        let coverages: Vec<Coverage> = report.iter().map(|track| track.coverage).collect();
+        assert_eq!(
+            coverages,
+            vec![
+                Coverage::Synced,
+                Coverage::Unsynced,
+                Coverage::Missing,
+                Coverage::Untagged
+            ]
+        );
+
+        let listed: Vec<&TrackCoverage> = report.iter().collect();
+        assert_eq!(
+            table(&listed),
+            vec![
+                "STATUS    ARTIST        TITLE",
+                "synced    Linkin Park   Numb",
+                "unsynced  Linkin Park   Faint",
+                "missing   宇多田ヒカル  First Love",
+                "untagged                ",
+            ]
+        );
+
+        let count = |coverage| report.iter().filter(|t| t.coverage == coverage).count();
+        let output = json(&listed, count);
+        assert_eq!(output["total"], 4);
+        assert_eq!(output["synced"], 1);
+        assert_eq!(output["unsynced"], 1);
+        assert_eq!(output["missing"], 1);
+        assert_eq!(output["untagged"], 1);
+        assert_eq!(output["tracks"][3]["status"], "untagged");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,403 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Write, stdin, stdout};
+use std::path::{Path, PathBuf};
+
+use crate::runtime::cmus::playing_song;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::lyric::Lyric;
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+const IMPORT_USAGE: &str = "Usage: ciryl import [OPTIONS] LYRIC_FILE
+       ciryl import [OPTIONS] --batch DIR
+
+Copy LYRIC_FILE in $LYRICS_DIR using cmus metadata to generate
+the MD5 digest as filename.
+
+With --batch, every \".lrc\" file in DIR is copied using its [ar:] and
+[ti:] tags, or its \"Artist - Title.lrc\" filename, instead.
+
+OPTIONS
+  -b, --batch DIR     Import all the lyric files in DIR.
+      --dry-run       Only report what a batch import would do.
+  -i, --info          Print information about destination, digest and input file.
+  -n, --no-confirm    Copy without asking to confirm.
+  -h, --help          Print this message.";
+
+struct ImportOptions {
+    file: Option<String>,
+    batch: Option<String>,
+    dry_run: bool,
+    info_only: bool,
+    no_confirm: bool,
+}
+
+/// What a batch import does with a single lyric file.
+#[derive(Debug, PartialEq)]
+enum BatchAction {
+    Import,
+    AlreadyPresent,
+    Conflict,
+    Duplicate(PathBuf),
+    Unknown,
+}
+
+struct BatchEntry {
+    source: PathBuf,
+    artist: String,
+    title: String,
+    destination: String,
+    action: BatchAction,
+}
+
+impl ImportOptions {
+    fn parse(args: &[String]) -> Result<Option<ImportOptions>> {
+        let mut options = ImportOptions {
+            file: None,
+            batch: None,
+            dry_run: false,
+            info_only: false,
+            no_confirm: false,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "-b" | "--batch" => match args.next() {
+                    Some(dir) => options.batch = Some(dir.to_string()),
+                    None => {
+                        return Err(RuntimeError::UsageError(
+                            "--batch requires a directory".to_string(),
+                        ));
+                    }
+                },
+                "--dry-run" => options.dry_run = true,
+                "-i" | "--info" => options.info_only = true,
+                "-n" | "--no-confirm" => options.no_confirm = true,
+                "-h" | "--help" => return Ok(None),
+                option if option.starts_with('-') => {
+                    return Err(RuntimeError::UsageError(format!(
+                        "unknown option '{}'",
+                        option
+                    )));
+                }
+                file => options.file = Some(file.to_string()),
+            }
+        }
+
+        Ok(Some(options))
+    }
+}
+
+fn confirm() -> Result<bool> {
+    print!("Confirm? (Y/n) ");
+    stdout().flush()?;
+
+    let mut answer = String::new();
+    stdin().read_line(&mut answer)?;
+
+    Ok(matches!(answer.trim(), "" | "y" | "Y"))
+}
+
+pub fn digest() -> Result<()> {
+    let song = playing_song()?;
+    println!("{}", Lyric::digest(&song.artist, &song.title));
+    Ok(())
+}
+
+pub fn import(args: &[String]) -> Result<()> {
+    let options = match ImportOptions::parse(args)? {
+        Some(options) => options,
+        None => {
+            println!("{}", IMPORT_USAGE);
+            return Ok(());
+        }
+    };
+
+    if let Some(dir) = &options.batch {
+        return import_batch(
+            dir,
+            &Lyric::lyrics_dirs()?,
+            options.dry_run,
+            options.no_confirm,
+        );
+    }
+
+    let song = playing_song()?;
+    let digest = Lyric::digest(&song.artist, &song.title);
+    let destination = Lyric::path(&song)?;
+    let file = options.file.unwrap_or_default();
+
+    let file_name = Path::new(&file)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    println!("  Input file: {}", file_name);
+    println!("      Digest: {}", digest);
+    println!(" Destination: {}", destination);
+
+    if options.info_only {
+        return Ok(());
+    }
+
+    if file.is_empty() {
+        return Err(RuntimeError::UsageError(
+            "LYRIC_FILE argument is required".to_string(),
+        ));
+    }
+
+    if !options.no_confirm && !confirm()? {
+        println!("Abort");
+        return Ok(());
+    }
+
+    Lyric::write(&destination, &fs::read(&file)?)?;
+    println!("Copied successfully");
+
+    Ok(())
+}
+
+/// Artist and title of a lyric file, read from its tags or its filename.
+fn artist_title(path: &Path, content: &str) -> Option<(String, String)> {
+    let artist = Lyric::find_tag(content, "ar");
+    let title = Lyric::find_tag(content, "ti");
+
+    if let (Some(artist), Some(title)) = (artist, title)
+        && !artist.is_empty()
+        && !title.is_empty()
+    {
+        return Some((artist.to_string(), title.to_string()));
+    }
+
+    let stem = path.file_stem()?.to_string_lossy();
+    let (artist, title) = stem.split_once(" - ")?;
+    let (artist, title) = (artist.trim(), title.trim());
+
+    if artist.is_empty() || title.is_empty() {
+        return None;
+    }
+
+    Some((artist.to_string(), title.to_string()))
+}
+
+/// What to do with each lyric file of `dir`. New lyrics go to the first of
+/// `lyrics_dirs`, lyrics already in any of them are compared instead.
+fn plan_batch(dir: &str, lyrics_dirs: &[String]) -> Result<Vec<BatchEntry>> {
+    let Some(lyric_folder) = lyrics_dirs.first() else {
+        return Err(RuntimeError::LyricDirEnvNotSet);
+    };
+
+    let mut sources: Vec<PathBuf> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.is_file())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "lrc"))
+        .collect();
+    sources.sort();
+
+    let mut planned: HashMap<String, PathBuf> = HashMap::new();
+    let mut entries: Vec<BatchEntry> = Vec::new();
+
+    for source in sources {
+        let content = fs::read(&source)?;
+
+        let Some((artist, title)) = artist_title(&source, &String::from_utf8_lossy(&content))
+        else {
+            entries.push(BatchEntry {
+                source,
+                artist: String::new(),
+                title: String::new(),
+                destination: String::new(),
+                action: BatchAction::Unknown,
+            });
+            continue;
+        };
+
+        let filename = format!("{}.lrc", Lyric::digest(&artist, &title));
+        let destination = lyrics_dirs
+            .iter()
+            .map(|dir| format!("{}/{}", dir, filename))
+            .find(|path| Path::new(path).exists())
+            .unwrap_or(format!("{}/{}", lyric_folder, filename));
+
+        let action = if let Some(first) = planned.get(&destination) {
+            BatchAction::Duplicate(first.clone())
+        } else {
+            planned.insert(destination.clone(), source.clone());
+            match fs::read(&destination) {
+                Ok(existing) if existing == content => BatchAction::AlreadyPresent,
+                Ok(_) => BatchAction::Conflict,
+                Err(_) => BatchAction::Import,
+            }
+        };
+
+        entries.push(BatchEntry {
+            source,
+            artist,
+            title,
+            destination,
+            action,
+        });
+    }
+
+    Ok(entries)
+}
+
+fn import_batch(dir: &str, lyrics_dirs: &[String], dry_run: bool, no_confirm: bool) -> Result<()> {
+    let entries = plan_batch(dir, lyrics_dirs)?;
+    let mut to_import: Vec<&BatchEntry> = Vec::new();
+
+    for entry in &entries {
+        let source = entry.source.display();
+        match &entry.action {
+            BatchAction::Import => {
+                println!("   import: {} ({} - {})", source, entry.artist, entry.title);
+                to_import.push(entry);
+            }
+            BatchAction::AlreadyPresent => {
+                println!("  present: {} in {}", source, entry.destination)
+            }
+            BatchAction::Conflict => {
+                println!(" conflict: {} differs from {}", source, entry.destination)
+            }
+            BatchAction::Duplicate(first) => println!(
+                "duplicate: {} is the same song as {}",
+                source,
+                first.display()
+            ),
+            BatchAction::Unknown => println!("  unknown: {} has no artist and title", source),
+        }
+    }
+
+    let count =
+        |action: fn(&BatchAction) -> bool| entries.iter().filter(|e| action(&e.action)).count();
+    println!(
+        "\n{} to import, {} already present, {} conflicts, {} duplicates, {} unknown",
+        to_import.len(),
+        count(|action| *action == BatchAction::AlreadyPresent),
+        count(|action| *action == BatchAction::Conflict),
+        count(|action| matches!(action, BatchAction::Duplicate(_))),
+        count(|action| *action == BatchAction::Unknown),
+    );
+
+    if dry_run || to_import.is_empty() {
+        return Ok(());
+    }
+
+    if !no_confirm && !confirm()? {
+        println!("Abort");
+        return Ok(());
+    }
+
+    for entry in &to_import {
+        Lyric::write(&entry.destination, &fs::read(&entry.source)?)?;
+    }
+    println!("Copied {} lyrics", to_import.len());
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::import::{BatchAction, artist_title, import_batch, plan_batch};
+    use crate::runtime::lyric::Lyric;
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn artist_title_from_tags() {
+        let path = Path::new("lyrics/Whatever - Else.lrc");
+        let content = "[ar:Sleep Token]\n[ti:Granite]\n[00:01.00] verse";
+        assert_eq!(
+            artist_title(path, content),
+            Some(("Sleep Token".to_string(), "Granite".to_string()))
+        );
+    }
+
+    #[test]
+    fn artist_title_from_filename() {
+        let content = "[ar:Sleep Token]\n[00:01.00] verse";
+
+        let path = Path::new("lyrics/Linkin Park - One Step Closer.lrc");
+        assert_eq!(
+            artist_title(path, content),
+            Some(("Linkin Park".to_string(), "One Step Closer".to_string()))
+        );
+
+        let path = Path::new("lyrics/AC-DC - Back In Black - Live.lrc");
+        assert_eq!(
+            artist_title(path, content),
+            Some(("AC-DC".to_string(), "Back In Black - Live".to_string()))
+        );
+
+        let path = Path::new("lyrics/untitled.lrc");
+        assert_eq!(artist_title(path, content), None);
+    }
+
+    #[test]
+    fn batch_in_several_dirs() {
+        let root = std::env::temp_dir().join(format!("ciryl-import-{}", std::process::id()));
+        let (source, first, second) = (root.join("new"), root.join("first"), root.join("second"));
+        for dir in [&source, &first, &second] {
+            fs::create_dir_all(dir).unwrap();
+        }
+
+        let lyric = |dir: &Path, artist: &str, title: &str| {
+            dir.join(format!("{}.lrc", Lyric::digest(artist, title)))
+        };
+        fs::write(source.join("Linkin Park - Numb.lrc"), "[00:01.00] numb").unwrap();
+        fs::write(source.join("Linkin Park - Faint.lrc"), "[00:01.00] faint").unwrap();
+        fs::write(source.join("Sleep Token - Granite.lrc"), "[00:01.00] new").unwrap();
+        fs::write(lyric(&second, "Linkin Park", "Numb"), "[00:01.00] numb").unwrap();
+        fs::write(lyric(&second, "Linkin Park", "Faint"), "[00:01.00] older").unwrap();
+
+        let dir = |path: &Path| path.to_string_lossy().to_string();
+        let lyrics_dirs = vec![dir(&first), dir(&second)];
+        let plan = plan_batch(&dir(&source), &lyrics_dirs).unwrap();
+        let actions: Vec<(&str, &BatchAction, &str)> = plan
+            .iter()
+            .map(|entry| {
+                (
+                    entry.title.as_str(),
+                    &entry.action,
+                    entry.destination.as_str(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            actions,
+            vec![
+                (
+                    "Faint",
+                    &BatchAction::Conflict,
+                    dir(&lyric(&second, "Linkin Park", "Faint")).as_str()
+                ),
+                (
+                    "Numb",
+                    &BatchAction::AlreadyPresent,
+                    dir(&lyric(&second, "Linkin Park", "Numb")).as_str()
+                ),
+                (
+                    "Granite",
+                    &BatchAction::Import,
+                    dir(&lyric(&first, "Sleep Token", "Granite")).as_str()
+                ),
+            ]
+        );
+
+        /* A dry run only reports */
+        import_batch(&dir(&source), &lyrics_dirs, true, true).unwrap();
+        assert_eq!(fs::read_dir(&first).unwrap().count(), 0);
+
+        import_batch(&dir(&source), &lyrics_dirs, false, true).unwrap();
+        assert_eq!(fs::read_dir(&first).unwrap().count(), 1);
+        assert_eq!(
+            fs::read_to_string(lyric(&second, "Linkin Park", "Faint")).unwrap(),
+            "[00:01.00] older"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,16 +1,223 @@
+mod check;
+mod coverage;
+mod import;
+mod now;
+mod print;
 mod runtime;
+mod stream;
+mod sync;
 
-use crate::runtime::{CirylRuntime, RuntimeReturn};
+use crate::runtime::CirylRuntime;
+use crate::runtime::config::{self, Config};
+use crate::runtime::error::RuntimeError;
+use crate::runtime::notifier::NotifyMode;
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let mut rt = CirylRuntime::new();
+const USAGE: &str = "Usage: ciryl [OPTIONS] [COMMAND] [ARGS]
 
-    loop {
-        match rt.task()? {
-            RuntimeReturn::Exit => break,
-            RuntimeReturn::Continue => {}
-        };
+Display the lyric of the song currently playing on cmus.
+
+COMMANDS
+  tui         Show the lyric of the playing song, the default.
+  print       Print the lyric of the playing song.
+  now         Print the verse being sung and exit.
+  stream      Write the verse being sung to stdout as it changes.
+  import      Copy a lyric file in $LYRICS_DIR for the playing song.
+  digest      Print MD5 digest of the current playing song artist-title.
+  coverage    Report which tracks of the cmus library lack a lyric.
+  check       Check that lyric files are well formed.
+  sync        Synchronize a plain-text lyric while the song plays.
+
+OPTIONS
+  -p, --player NAME       Player to follow, only cmus is supported.
+  -l, --lyrics-dir DIR    Look up the lyrics in DIR, can be repeated.
+  -s, --socket PATH       Path of the cmus socket.
+  -c, --config FILE       Read the settings from FILE.
+  -n, --notify MODE       Send desktop notifications on each song or
+                          verse, MODE being off, song or verse.
+  -V, --version           Print the version.
+  -h, --help              Print this message.
+
+Settings are read from $XDG_CONFIG_HOME/ciryl/config.toml, or
+~/.config/ciryl/config.toml, overridden by the environment and then by
+the options. Run \"ciryl COMMAND --help\" for the options of a command.";
+
+/// Options given before the command, overriding the configuration.
+#[derive(Debug, Default, PartialEq)]
+struct Options {
+    player: Option<String>,
+    lyrics_dirs: Vec<String>,
+    socket: Option<String>,
+    config: Option<String>,
+    notify: Option<String>,
+}
+
+impl Options {
+    /// Options leading `args`, and the command with its arguments.
+    fn parse(args: &[String]) -> Result<(Options, &[String]), RuntimeError> {
+        let mut options = Options::default();
+        let mut rest = args;
+
+        while let [option, tail @ ..] = rest {
+            let takes_value = matches!(
+                option.as_str(),
+                "-p" | "--player"
+                    | "-l"
+                    | "--lyrics-dir"
+                    | "-s"
+                    | "--socket"
+                    | "-c"
+                    | "--config"
+                    | "-n"
+                    | "--notify"
+            );
+            if !takes_value {
+                break;
+            }
+
+            let [value, tail @ ..] = tail else {
+                return Err(RuntimeError::UsageError(format!(
+                    "{} requires a value",
+                    option
+                )));
+            };
+            let value = value.to_string();
+
+            match option.as_str() {
+                "-p" | "--player" => options.player = Some(value),
+                "-l" | "--lyrics-dir" => options.lyrics_dirs.push(value),
+                "-s" | "--socket" => options.socket = Some(value),
+                "-n" | "--notify" => options.notify = Some(value),
+                _ => options.config = Some(value),
+            }
+            rest = tail;
+        }
+
+        Ok((options, rest))
+    }
+
+    /// Override the settings of `config` with the options.
+    fn apply(self, config: &mut Config) -> Result<(), RuntimeError> {
+        if let Some(player) = self.player.as_deref()
+            && player != "cmus"
+        {
+            return Err(RuntimeError::UsageError(format!(
+                "unsupported player '{}', only cmus is supported",
+                player
+            )));
+        }
+
+        if !self.lyrics_dirs.is_empty() {
+            config.lyrics_dirs = self.lyrics_dirs;
+        }
+        if self.socket.is_some() {
+            config.socket = self.socket;
+        }
+        if let Some(mode) = self.notify {
+            config.notify = NotifyMode::parse(&mode).ok_or(RuntimeError::UsageError(format!(
+                "unknown notification mode '{}', expected off, song or verse",
+                mode
+            )))?;
+        }
+
+        Ok(())
     }
+}
+
+fn tui() -> Result<(), RuntimeError> {
+    let mut rt = CirylRuntime::new()?;
+    runtime::run(|| rt.task())
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-    Ok(())
+    let mut now = false;
+    let result = Options::parse(&args).and_then(|(options, command_args)| {
+        now = command_args.first().is_some_and(|command| command == "now");
+
+        /* Help and version don't need a valid configuration */
+        match command_args.first().map(String::as_str) {
+            Some("-h" | "--help") => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            Some("-V" | "--version") => {
+                println!("ciryl {}", env!("CARGO_PKG_VERSION"));
+                return Ok(());
+            }
+            _ => {}
+        }
+
+        let mut loaded = Config::load(options.config.as_deref())?;
+        options.apply(&mut loaded)?;
+        config::set(loaded);
+        command(command_args)
+    });
+
+    if let Err(error) = result {
+        eprintln!("ciryl: {}", error);
+        /* Only now tells its failures apart, for scripts and prompts */
+        std::process::exit(match now {
+            true => error.exit_code(),
+            false => 1,
+        });
+    }
+}
+
+fn command(args: &[String]) -> Result<(), RuntimeError> {
+    match args.first().map(String::as_str) {
+        None | Some("tui") => tui(),
+        Some("print") => print::print(&args[1..]),
+        Some("now") => now::now(&args[1..]),
+        Some("stream") => stream::stream(&args[1..]),
+        Some("import") => import::import(&args[1..]),
+        Some("digest") => import::digest(),
+        Some("coverage") => coverage::coverage(&args[1..]),
+        Some("check") => check::check(&args[1..]),
+        Some("sync") => sync::sync(&args[1..]),
+        Some(option) if option.starts_with('-') => Err(RuntimeError::UsageError(format!(
+            "unknown option '{}'",
+            option
+        ))),
+        Some(command) => Err(RuntimeError::UsageError(format!(
+            "unknown command '{}'",
+            command
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::Options;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn global_options() {
+        let args = args(&[
+            "-l",
+            "/a",
+            "--lyrics-dir",
+            "/b",
+            "--socket",
+            "/s",
+            "check",
+            "-l",
+        ]);
+        let (options, rest) = Options::parse(&args).unwrap();
+
+        assert_eq!(options.lyrics_dirs, vec!["/a", "/b"]);
+        assert_eq!(options.socket.as_deref(), Some("/s"));
+        assert_eq!(options.config, None);
+        assert_eq!(rest, &args[6..]);
+
+        let (options, rest) = Options::parse(&[]).unwrap();
+        assert_eq!(options, Options::default());
+        assert!(rest.is_empty());
+
+        assert!(Options::parse(&["--config".to_string()]).is_err());
+    }
 }
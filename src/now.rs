@@ -0,0 +1,120 @@
+use crate::runtime::bars::Bars;
+use crate::runtime::cmus::playing_song;
+use crate::runtime::config;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::lyric::Lyric;
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+const NOW_USAGE: &str = "Usage: ciryl now [OPTIONS]
+
+Print the verse being sung on cmus and exit.
+
+OPTIONS
+  -f, --format FORMAT   Format of the verse, \"{verse}\" by default. The
+                        fields of the header and footer can be used too,
+                        along with {previous} and {next}.
+  -B, --before N        Print N verses before the sung one.
+  -A, --after N         Print N verses after the sung one.
+  -h, --help            Print this message.
+
+EXIT STATUS
+  0    A verse is being sung.
+  2    cmus is not running or no song is playing.
+  3    The song has no synced lyric.
+  4    Instrumental gap, no verse is being sung.
+  1    Any other error.";
+
+struct NowOptions {
+    format: String,
+    before: usize,
+    after: usize,
+}
+
+impl NowOptions {
+    fn parse(args: &[String]) -> Result<Option<NowOptions>> {
+        let mut options = NowOptions {
+            format: "{verse}".to_string(),
+            before: 0,
+            after: 0,
+        };
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next().ok_or(RuntimeError::UsageError(format!(
+                    "{} requires a value",
+                    arg
+                )))
+            };
+            let count = |value: &String| {
+                value.parse().map_err(|_| {
+                    RuntimeError::UsageError(format!("invalid number of verses '{}'", value))
+                })
+            };
+
+            match arg.as_str() {
+                "-f" | "--format" => options.format = value()?.to_string(),
+                "-B" | "--before" => options.before = count(value()?)?,
+                "-A" | "--after" => options.after = count(value()?)?,
+                "-h" | "--help" => return Ok(None),
+                arg => {
+                    return Err(RuntimeError::UsageError(format!(
+                        "unexpected argument '{}'",
+                        arg
+                    )));
+                }
+            }
+        }
+
+        Ok(Some(options))
+    }
+}
+
+pub fn now(args: &[String]) -> Result<()> {
+    let Some(options) = NowOptions::parse(args)? else {
+        println!("{}", NOW_USAGE);
+        return Ok(());
+    };
+
+    let song = playing_song()?;
+    let mut lyric = Lyric::new();
+    lyric.parse(&song)?;
+
+    let verses = lyric.get_text();
+    if verses.is_empty() {
+        return Err(RuntimeError::LyricNotFound);
+    }
+
+    let position = song
+        .position
+        .saturating_add_signed(lyric.offset() + config::get().global_offset);
+    let index = lyric
+        .sung_verse(position)
+        .ok_or(RuntimeError::InstrumentalGap)?;
+
+    let verse = |index: Option<usize>| index.and_then(|index| verses.get(index)).copied();
+    let field = |name: &str| match name {
+        "verse" => verse(Some(index)).map(str::to_string),
+        "previous" => Some(verse(index.checked_sub(1)).unwrap_or("").to_string()),
+        "next" => Some(verse(Some(index + 1)).unwrap_or("").to_string()),
+        "artist" => Some(song.artist.clone()),
+        "title" => Some(song.title.clone()),
+        "album" => Some(song.album.clone()),
+        "status" => Some(song.status.clone()),
+        "elapsed" => Some(Bars::format_time(song.position)),
+        "duration" => Some(Bars::format_time(song.duration)),
+        "progress" => Some(Bars::progress(song.position, song.duration)),
+        _ => None,
+    };
+
+    for before in &verses[index.saturating_sub(options.before)..index] {
+        println!("{}", before);
+    }
+    println!("{}", Bars::render(&options.format, &field));
+    for after in verses.iter().skip(index + 1).take(options.after) {
+        println!("{}", after);
+    }
+
+    Ok(())
+}
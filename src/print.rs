@@ -0,0 +1,59 @@
+use std::io::{ErrorKind, Write, stdout};
+
+use crate::runtime::cmus::playing_song;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::lyric::Lyric;
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+const PRINT_USAGE: &str = "Usage: ciryl print [OPTIONS]
+
+Print the lyric of the song playing on cmus, one verse per line.
+
+OPTIONS
+  -t, --timestamps    Print the timestamp of each verse.
+  -h, --help          Print this message.";
+
+pub fn print(args: &[String]) -> Result<()> {
+    let mut timestamps = false;
+
+    for arg in args {
+        match arg.as_str() {
+            "-t" | "--timestamps" => timestamps = true,
+            "-h" | "--help" => {
+                println!("{}", PRINT_USAGE);
+                return Ok(());
+            }
+            arg => {
+                return Err(RuntimeError::UsageError(format!(
+                    "unexpected argument '{}'",
+                    arg
+                )));
+            }
+        }
+    }
+
+    let song = playing_song()?;
+    let mut lyric = Lyric::new();
+    lyric.parse(&song)?;
+
+    let mut out = stdout().lock();
+    let written = lyric
+        .get_text()
+        .iter()
+        .enumerate()
+        .try_for_each(
+            |(index, verse)| match lyric.timestamp(index).filter(|_| timestamps) {
+                Some(timestamp) => {
+                    writeln!(out, "[{}] {}", Lyric::format_timestamp(timestamp), verse)
+                }
+                None => writeln!(out, "{}", verse),
+            },
+        );
+
+    /* The reader may stop early, as `head` does */
+    match written {
+        Err(error) if error.kind() != ErrorKind::BrokenPipe => Err(error.into()),
+        _ => Ok(()),
+    }
+}
@@ -1,12 +1,35 @@
-mod cmus;
-mod error;
+pub mod bars;
+pub mod cmus;
+pub mod config;
+mod dbus;
+mod editor;
+pub mod error;
 mod gui;
-mod lyric;
+mod keymap;
+pub mod lyric;
+pub mod notifier;
+mod search;
+mod sink;
+mod stream;
+mod sync;
+mod tui;
+mod watcher;
+
+use std::time::Instant;
 
 use cmus::{Cmus, PlayerSongInfo};
 use error::RuntimeError;
-use gui::Gui;
+use gui::theme::Theme;
+use gui::{Gui, GuiEvent, Key};
+use keymap::{Action, Keymap};
 use lyric::Lyric;
+use search::Search;
+use sink::{Event, Sink, State};
+use tui::Tui;
+use watcher::DirWatcher;
+
+pub use stream::{StreamFormat, StreamRuntime};
+pub use sync::SyncRuntime;
 
 type Result<T> = std::result::Result<T, RuntimeError>;
 
@@ -15,10 +38,24 @@ pub enum RuntimeReturn {
     Exit,
 }
 
-#[derive(PartialEq)]
+/// Run `task` until it asks to exit.
+pub fn run(mut task: impl FnMut() -> Result<RuntimeReturn>) -> Result<()> {
+    loop {
+        match task()? {
+            RuntimeReturn::Exit => break,
+            RuntimeReturn::Continue => {}
+        };
+    }
+
+    Ok(())
+}
+
+#[derive(PartialEq, Clone, Copy)]
 enum RuntimeUpdate {
     NewSong,
     NewIndex,
+    /* Same verse, but the position or status of the song changed */
+    NewState,
     Nop,
     LyricNotFound,
     LyricDirNotSet,
@@ -27,29 +64,68 @@ enum RuntimeUpdate {
     DisplayError,
 }
 
-pub struct CirylRuntime {
+impl RuntimeUpdate {
+    /// Update to remember as the last one, errors being all shown alike.
+    fn settled(self) -> RuntimeUpdate {
+        match self {
+            RuntimeUpdate::CmusError
+            | RuntimeUpdate::ParseError
+            | RuntimeUpdate::LyricDirNotSet
+            | RuntimeUpdate::LyricNotFound
+            | RuntimeUpdate::DisplayError => RuntimeUpdate::DisplayError,
+            update => update,
+        }
+    }
+}
+
+/// Song playing on cmus, its lyric and the verse being sung.
+struct Tracker {
     player: Cmus,
     lyric: Lyric,
     song: PlayerSongInfo,
     fixed_index: usize,
-    initialized: bool,
     last_update: RuntimeUpdate,
+    lyric_watchers: Vec<DirWatcher>,
+    /* Milliseconds added to the position of every song */
+    global_offset: isize,
 }
 
-impl CirylRuntime {
-    pub fn new() -> CirylRuntime {
-        CirylRuntime {
-            player: Cmus::new(),
+impl Tracker {
+    fn new() -> Result<Tracker> {
+        Ok(Tracker {
+            player: Cmus::new()?,
             lyric: Lyric::new(),
             song: PlayerSongInfo::new(),
             fixed_index: 0,
-            initialized: false,
             last_update: RuntimeUpdate::Nop,
+            lyric_watchers: Vec::new(),
+            global_offset: config::get().global_offset,
+        })
+    }
+
+    fn state(&self) -> State<'_> {
+        State {
+            song: &self.song,
+            lyric: &self.lyric,
+            index: self.fixed_index,
+            global_offset: self.global_offset,
         }
     }
 
+    /// Song position shifted by the lyric and global offsets.
+    fn lyric_position(&self, position: usize) -> usize {
+        position.saturating_add_signed(self.lyric.offset() + self.global_offset)
+    }
+
+    /// Update the song from cmus, settling the update as the last one.
     fn update(&mut self) -> RuntimeUpdate {
-        if let Err(_) = self.player.update() {
+        let update = self.poll();
+        self.last_update = update.settled();
+        update
+    }
+
+    fn poll(&mut self) -> RuntimeUpdate {
+        if self.player.update().is_err() {
             return match self.last_update {
                 RuntimeUpdate::DisplayError => RuntimeUpdate::DisplayError,
                 _ => RuntimeUpdate::CmusError,
@@ -66,17 +142,44 @@ impl CirylRuntime {
             }
         };
 
+        /* A file appeared in a lyrics directory: retry the lookup */
+        let mut changed = false;
+        for watcher in &mut self.lyric_watchers {
+            changed |= watcher.changed();
+        }
+        if changed {
+            self.song = PlayerSongInfo::new();
+        }
+
         if self.song != song {
             self.song = song.clone();
 
+            /* Start watching before the lookup so no file creation is missed.
+             * The lyric files, sidecar included, are watched too: a copy
+             * creates an empty file first and writes the verses into it
+             * afterwards */
+            let watchers = [Lyric::lyrics_dirs(), Lyric::paths(&song)]
+                .into_iter()
+                .flat_map(|paths| paths.unwrap_or_default())
+                .map(DirWatcher::new)
+                .collect();
+            self.lyric_watchers.clear();
+
             match self.lyric.parse(&song) {
+                /* Keep watching until the lyric has verses */
+                Ok(_) if self.lyric.get_text().is_empty() => self.lyric_watchers = watchers,
                 Ok(_) => {}
-                Err(RuntimeError::LyricNotFound) => return RuntimeUpdate::LyricNotFound,
+                Err(RuntimeError::LyricNotFound) => {
+                    self.lyric_watchers = watchers;
+                    return RuntimeUpdate::LyricNotFound;
+                }
                 Err(RuntimeError::LyricDirEnvNotSet) => return RuntimeUpdate::LyricDirNotSet,
                 Err(_) => return RuntimeUpdate::ParseError,
             };
 
-            self.fixed_index = self.lyric.get_singed_verse_index(song.position);
+            self.fixed_index = self
+                .lyric
+                .get_singed_verse_index(self.lyric_position(song.position));
             return RuntimeUpdate::NewSong;
         }
 
@@ -84,68 +187,233 @@ impl CirylRuntime {
             return RuntimeUpdate::DisplayError;
         }
 
-        let fixed_index = self.lyric.get_singed_verse_index(song.position);
+        /* Same song, keep its position and status up to date for the bars */
+        let moved = self.song.position != song.position || self.song.status != song.status;
+        self.song = song;
+
+        let fixed_index = self
+            .lyric
+            .get_singed_verse_index(self.lyric_position(self.song.position));
         if fixed_index != self.fixed_index {
             self.fixed_index = fixed_index;
             return RuntimeUpdate::NewIndex;
         }
 
-        RuntimeUpdate::Nop
+        match moved {
+            true => RuntimeUpdate::NewState,
+            false => RuntimeUpdate::Nop,
+        }
+    }
+
+    /// Update the song and send the event of the update to `sink`.
+    fn follow(&mut self, sink: &mut dyn Sink) -> Result<RuntimeUpdate> {
+        let previous = self.fixed_index;
+        let update = self.update();
+
+        if let Some(event) = Event::of(update, self.state(), previous) {
+            sink.event(&event)?;
+        }
+        Ok(update)
+    }
+}
+
+pub struct CirylRuntime {
+    tracker: Tracker,
+    tui: Tui,
+    /* Outputs besides the TUI, such as the desktop notifications */
+    sinks: Vec<Box<dyn Sink>>,
+    /* When the player was last polled, the frames of the scrolling between */
+    polled: Option<Instant>,
+    initialized: bool,
+}
+
+/* The TUI first, then the other sinks, which can't stop the TUI */
+struct TuiSinks<'a> {
+    tui: &'a mut Tui,
+    sinks: &'a mut Vec<Box<dyn Sink>>,
+}
+
+impl Sink for TuiSinks<'_> {
+    fn event(&mut self, event: &Event) -> Result<()> {
+        self.tui.event(event)?;
+
+        if let Err(error) = self.sinks.event(event) {
+            self.tui.message = match error {
+                RuntimeError::NotifyError(error) => format!("Can't notify: {}", error),
+                error => error.to_string(),
+            };
+            self.tui.redraw_status = true;
+        }
+        Ok(())
+    }
+}
+
+impl CirylRuntime {
+    pub fn new() -> Result<CirylRuntime> {
+        Ok(CirylRuntime {
+            tracker: Tracker::new()?,
+            tui: Tui::new(),
+            sinks: sink::from_config(),
+            polled: None,
+            initialized: false,
+        })
+    }
+
+    /// Seek cmus to the verse viewed while browsing.
+    fn seek_view(&mut self) -> Result<()> {
+        match self.tui.view() {
+            Some(view) => self.seek_verse(view),
+            None => Ok(()),
+        }
+    }
+
+    /// Seek the player to the verse at `index` and follow the song from it.
+    fn seek_verse(&mut self, index: usize) -> Result<()> {
+        let tracker = &mut self.tracker;
+        let Some(timestamp) = tracker.lyric.timestamp(index) else {
+            return Ok(());
+        };
+
+        /* The verse is shown once the position shifted by the offsets reaches it */
+        let position =
+            timestamp.saturating_add_signed(-(tracker.lyric.offset() + tracker.global_offset));
+        self.tui.message = match tracker.player.seek(position) {
+            Ok(_) => String::new(),
+            Err(error) => format!("Can't seek: {}", error),
+        };
+
+        self.tui.browse = None;
+        self.tui.redraw_lyric(&self.tracker.state())
+    }
+
+    fn shift_offset(&mut self, song: isize, global: isize) {
+        let lyric = &mut self.tracker.lyric;
+        lyric.set_offset(lyric.offset() + song);
+        self.tracker.global_offset += global;
+        self.tui.message.clear();
+        self.tui.redraw_status = true;
+    }
+
+    fn save_offset(&mut self) {
+        let tracker = &self.tracker;
+        self.tui.message = match Lyric::save_offset(&tracker.song, tracker.lyric.offset()) {
+            Ok(path) => format!("Offset saved to {}", path),
+            Err(error) => format!("Can't save the offset: {}", error),
+        };
+        self.tui.redraw_status = true;
+    }
+
+    fn act(&mut self, action: Action) -> Result<RuntimeReturn> {
+        let state = self.tracker.state();
+        let tui = &mut self.tui;
+
+        match action {
+            Action::Quit => {
+                Gui::terminate()?;
+                return Ok(RuntimeReturn::Exit);
+            }
+            /* Retry song parsing */
+            Action::Retry => self.tracker.song = PlayerSongInfo::new(),
+            /* Shift the song lyric, or the lyric of every song, by 100 ms */
+            Action::SongEarlier => self.shift_offset(config::get().offset_step, 0),
+            Action::SongLater => self.shift_offset(-config::get().offset_step, 0),
+            Action::ResetSongOffset => self.shift_offset(-self.tracker.lyric.offset(), 0),
+            Action::GlobalEarlier => self.shift_offset(0, config::get().offset_step),
+            Action::GlobalLater => self.shift_offset(0, -config::get().offset_step),
+            Action::SaveOffset => self.save_offset(),
+            /* Browse the lyric, following the song again on Follow */
+            Action::NextVerse => tui.browse(&state, 1)?,
+            Action::PreviousVerse => tui.browse(&state, -1)?,
+            Action::NextPage => tui.browse(&state, Gui::page_size()? as isize)?,
+            Action::PreviousPage => tui.browse(&state, -(Gui::page_size()? as isize))?,
+            Action::FirstVerse => tui.browse(&state, isize::MIN)?,
+            Action::LastVerse => tui.browse(&state, isize::MAX)?,
+            Action::Follow => tui.clear_search(&state)?,
+            Action::Seek => self.seek_view()?,
+            Action::Search if tui.showing_lyric() => {
+                tui.search = Some(Search::new(tui.view().unwrap_or(state.index)));
+                tui.redraw_status = true;
+            }
+            Action::NextMatch => tui.browse_match(&state, true)?,
+            Action::PreviousMatch => tui.browse_match(&state, false)?,
+            Action::Help if tui.showing_lyric() => tui.open_help()?,
+            Action::Search | Action::Help => {}
+        }
+
+        Ok(RuntimeReturn::Continue)
     }
 
     pub fn task(&mut self) -> Result<RuntimeReturn> {
+        let result = self.step();
+        /* The error is printed once the terminal is usable again */
+        if result.is_err() {
+            Gui::restore();
+        }
+        result
+    }
+
+    fn step(&mut self) -> Result<RuntimeReturn> {
         if !self.initialized {
-            Gui::initialize()?;
+            self.tui.keymap = Keymap::from_config()?;
+            Gui::initialize(Theme::from_config()?)?;
             self.initialized = true;
         }
 
-        let update = self.update();
+        /* The player is polled once per tick, even while scrolling on frames */
+        let tick = config::get().tick;
+        let elapsed = self.polled.map_or(tick, |polled| polled.elapsed());
+        if elapsed >= tick {
+            self.polled = Some(Instant::now());
+            self.tracker.follow(&mut TuiSinks {
+                tui: &mut self.tui,
+                sinks: &mut self.sinks,
+            })?;
+            self.tui.tick(&self.tracker.state())?;
+        } else {
+            self.tui.frame(&self.tracker.state())?;
+        }
 
-        match update {
-            RuntimeUpdate::NewSong => {
-                Gui::clear_screen()?;
-                Gui::print_vector(&self.lyric.get_text(), self.fixed_index)?;
-            }
-            RuntimeUpdate::NewIndex => Gui::print_vector(&self.lyric.get_text(), self.fixed_index)?,
-            RuntimeUpdate::CmusError => Gui::print_general_error("Can't connect to CMUS socket")?,
-            RuntimeUpdate::ParseError => Gui::print_general_error("Can't parse playing song")?,
-            RuntimeUpdate::LyricDirNotSet => {
-                Gui::print_general_error("LYRIC_DIR environment directory not set")?
-            }
-            RuntimeUpdate::LyricNotFound => {
-                Gui::print_lyric_not_found_error(&self.song.artist, &self.song.title)?
+        let until_poll = tick.saturating_sub(self.polled.map_or(tick, |polled| polled.elapsed()));
+        if let Some(event) = Gui::pool_event(self.tui.timeout().min(until_poll))? {
+            let state = self.tracker.state();
+
+            /* Keys editing the prompt, the others keep their action */
+            if self.tui.editing()
+                && let GuiEvent::Key(press) = event
+                && (press.text().is_some()
+                    || matches!(press.key, Key::Backspace | Key::Enter | Key::Esc))
+            {
+                self.tui.search_input(&state, press)?;
+                return Ok(RuntimeReturn::Continue);
             }
-            RuntimeUpdate::DisplayError => {}
-            RuntimeUpdate::Nop => {}
-        }
 
-        self.last_update = match update {
-            RuntimeUpdate::CmusError
-            | RuntimeUpdate::ParseError
-            | RuntimeUpdate::LyricDirNotSet
-            | RuntimeUpdate::LyricNotFound
-            | RuntimeUpdate::DisplayError => RuntimeUpdate::DisplayError,
-            RuntimeUpdate::NewSong => RuntimeUpdate::NewSong,
-            RuntimeUpdate::NewIndex => RuntimeUpdate::NewIndex,
-            RuntimeUpdate::Nop => RuntimeUpdate::Nop,
-        };
+            /* Any key closes the help, leaving the view as it was */
+            if self.tui.help && !matches!(event, GuiEvent::Terminate | GuiEvent::Resize) {
+                self.tui.close_help(&state)?;
+                return Ok(RuntimeReturn::Continue);
+            }
 
-        match Gui::pool_keyboard()? {
-            Some(key) => {
-                match key {
-                    /* Press 'q' to quit */
-                    'q' => {
-                        Gui::terminate()?;
-                        return Ok(RuntimeReturn::Exit);
-                    },
-                    /* Press 'r' to retry song parsing */
-                    'r' => {
-                        self.song = PlayerSongInfo::new();
+            match event {
+                GuiEvent::Terminate => {
+                    Gui::terminate()?;
+                    return Ok(RuntimeReturn::Exit);
+                }
+                /* Redraw everything when the terminal is resized */
+                GuiEvent::Resize => self.tui.redraw(&state)?,
+                /* Click a verse to seek to it */
+                GuiEvent::Click(row) => {
+                    if let Some(verse) = Gui::verse_at(row)
+                        && self.tui.showing_lyric()
+                    {
+                        self.seek_verse(verse)?;
                     }
-                    _ => {},
-                };
-            }
-            None => {}
+                }
+                event => {
+                    if let Some(action) = self.tui.keymap.action(event) {
+                        return self.act(action);
+                    }
+                }
+            };
         }
 
         Ok(RuntimeReturn::Continue)
@@ -0,0 +1,146 @@
+use crate::runtime::config;
+
+/// Format strings of the header and footer rows of the TUI.
+///
+/// `{name}` placeholders are replaced by the value of the field `name`, and
+/// text between `[` and `]` is left out when a field inside it is empty.
+pub struct Bars {
+    pub header: String,
+    pub footer: String,
+}
+
+impl Bars {
+    pub const HEADER: &str = "{artist} - {title}[ - {album}]";
+    pub const FOOTER: &str = "{status} {elapsed} {progress} {duration}[ | {source}][ | song {offset}][ | global {global_offset}]";
+    const PROGRESS_WIDTH: usize = 20;
+
+    /// Format strings of the configuration.
+    pub fn from_config() -> Bars {
+        Bars {
+            header: config::get().header.clone(),
+            footer: config::get().footer.clone(),
+        }
+    }
+
+    /// Replace the placeholders of `format` with the values of `field`.
+    /// Unknown fields are kept as they are.
+    pub fn render(format: &str, field: &dyn Fn(&str) -> Option<String>) -> String {
+        let mut rendered = String::new();
+        /* Text of the optional group and whether one of its fields is empty */
+        let mut group: Option<(String, bool)> = None;
+        let mut rest = format;
+
+        while let Some(c) = rest.chars().next() {
+            let mut length = c.len_utf8();
+            let mut empty = false;
+
+            let text = match c {
+                '{' if rest.contains('}') => {
+                    let end = rest.find('}').unwrap_or(0);
+                    length = end + 1;
+                    match field(&rest[1..end]) {
+                        Some(value) => {
+                            empty = value.is_empty();
+                            value
+                        }
+                        None => rest[..length].to_string(),
+                    }
+                }
+                '[' if group.is_none() => {
+                    group = Some((String::new(), false));
+                    String::new()
+                }
+                ']' if group.is_some() => {
+                    if let Some((text, false)) = group.take() {
+                        rendered.push_str(&text);
+                    }
+                    String::new()
+                }
+                c => c.to_string(),
+            };
+
+            match &mut group {
+                Some((group_text, group_empty)) => {
+                    group_text.push_str(&text);
+                    *group_empty |= empty;
+                }
+                None => rendered.push_str(&text),
+            }
+            rest = &rest[length..];
+        }
+
+        /* An unclosed group is kept as plain text */
+        if let Some((text, _)) = group {
+            rendered.push('[');
+            rendered.push_str(&text);
+        }
+
+        rendered
+    }
+
+    /// Format milliseconds as "m:ss".
+    pub fn format_time(time: usize) -> String {
+        format!("{}:{:02}", time / 60000, time / 1000 % 60)
+    }
+
+    /// Bar filled proportionally to `position` over `duration`.
+    pub fn progress(position: usize, duration: usize) -> String {
+        let filled = match duration {
+            0 => 0,
+            _ => (position.min(duration) * Bars::PROGRESS_WIDTH) / duration,
+        };
+
+        "━".repeat(filled) + &"─".repeat(Bars::PROGRESS_WIDTH - filled)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::bars::Bars;
+
+    fn field(name: &str) -> Option<String> {
+        match name {
+            "artist" => Some("Sleep Token".to_string()),
+            "title" => Some("Granite".to_string()),
+            "album" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn render_fields() {
+        assert_eq!(
+            Bars::render("{artist} - {title}", &field),
+            "Sleep Token - Granite"
+        );
+        assert_eq!(
+            Bars::render("{artist} {unknown}", &field),
+            "Sleep Token {unknown}"
+        );
+        assert_eq!(Bars::render("{artist} {", &field), "Sleep Token {");
+    }
+
+    #[test]
+    fn render_optional_groups() {
+        assert_eq!(
+            Bars::render("{artist}[ - {album}][ ({title})]", &field),
+            "Sleep Token (Granite)"
+        );
+        assert_eq!(Bars::render("[no fields]", &field), "no fields");
+        assert_eq!(Bars::render("{title} [open", &field), "Granite [open");
+    }
+
+    #[test]
+    fn time_and_progress() {
+        assert_eq!(Bars::format_time(214_999), "3:34");
+        assert_eq!(Bars::format_time(5_000), "0:05");
+
+        assert_eq!(Bars::progress(0, 0), "─".repeat(20));
+        assert_eq!(
+            Bars::progress(50_000, 200_000),
+            "━".repeat(5) + &"─".repeat(15)
+        );
+        assert_eq!(Bars::progress(300_000, 200_000), "━".repeat(20));
+    }
+}
@@ -1,8 +1,9 @@
-use std::env;
 use std::io::prelude::*;
 use std::os::unix::net::UnixStream;
+use std::time::Instant;
 
 use crate::runtime::RuntimeError;
+use crate::runtime::config;
 
 type Result<T> = std::result::Result<T, RuntimeError>;
 
@@ -10,7 +11,14 @@ type Result<T> = std::result::Result<T, RuntimeError>;
 pub struct PlayerSongInfo {
     pub title: String,
     pub artist: String,
+    pub album: String,
+    /* Path of the audio file, empty when unknown */
+    pub file: String,
+    /* Position and duration in milliseconds */
     pub position: usize,
+    pub duration: usize,
+    /* "playing", "paused" or "stopped" */
+    pub status: String,
 }
 
 impl PlayerSongInfo {
@@ -18,7 +26,11 @@ impl PlayerSongInfo {
         PlayerSongInfo {
             title: String::new(),
             artist: String::new(),
+            album: String::new(),
+            file: String::new(),
             position: 0,
+            duration: 0,
+            status: String::new(),
         }
     }
 }
@@ -27,74 +39,165 @@ impl PartialEq for PlayerSongInfo {
     fn eq(&self, other: &Self) -> bool {
         self.title == other.title && self.artist == other.artist
     }
-
-    fn ne(&self, other: &Self) -> bool {
-        self.title != other.title || self.artist != other.artist
-    }
 }
 
 impl Eq for PlayerSongInfo {}
 
+/// A track of the cmus library.
+pub struct LibraryTrack {
+    pub file: String,
+    pub title: String,
+    pub artist: String,
+}
+
 pub struct Cmus {
     socket_path: String,
     status: String,
+    /* Position and status last reported by cmus, and when they changed */
+    reported: (String, String),
+    reported_at: Instant,
 }
 
-const XDG_RUNTIME_DIR: &str = "XDG_RUNTIME_DIR";
-
 impl Cmus {
-    pub fn new() -> Cmus {
-        let mut socket_path: String = match env::var(XDG_RUNTIME_DIR) {
-            Ok(path) => path,
-            Err(error) => {
-                eprintln!("Error reading environment variable: {}", error);
-                std::process::exit(1);
-            }
-        };
+    pub fn new() -> Result<Cmus> {
+        let socket_path = config::get()
+            .socket
+            .clone()
+            .ok_or(RuntimeError::PlayerError(
+                "cmus socket not set, set XDG_RUNTIME_DIR or player.socket".to_string(),
+            ))?;
         let status = String::new();
-        socket_path.push_str("/cmus-socket");
 
-        Cmus {
+        Ok(Cmus {
             socket_path,
             status,
+            reported: (String::new(), String::new()),
+            reported_at: Instant::now(),
+        })
+    }
+
+    /// Playing position in milliseconds.
+    ///
+    /// cmus reports whole seconds only, so while playing the time elapsed
+    /// since the reported second changed is added to it.
+    fn interpolated_position(&self, seconds: usize) -> usize {
+        let mut position = seconds * 1000;
+
+        if self.reported.1 == "playing" {
+            let elapsed = self.reported_at.elapsed().as_millis() as usize;
+            position += elapsed.min(999);
         }
+
+        position
     }
 
     pub fn playing_song_metadata(&self) -> Result<PlayerSongInfo> {
         let title = self.parse_status("tag title");
         let artist = self.parse_status("tag artist");
         let position: usize = self.parse_status("position").parse()?;
-        let position = position * 1000;
+        let position = self.interpolated_position(position);
+        let duration = self.parse_status("duration").parse().unwrap_or(0) * 1000;
 
         Ok(PlayerSongInfo {
             title,
             artist,
+            album: self.parse_status("tag album"),
+            file: self.parse_status("file"),
             position,
+            duration,
+            status: self.parse_status("status"),
         })
     }
 
     pub fn update(&mut self) -> Result<()> {
-        let mut response = [0; 2048];
+        self.status = self.command("status")?;
+
+        let reported = (self.parse_status("position"), self.parse_status("status"));
+        if reported != self.reported {
+            self.reported = reported;
+            self.reported_at = Instant::now();
+        }
+
+        Ok(())
+    }
+
+    /// Seek the playing song to `position` milliseconds. cmus seeks to
+    /// whole seconds only, so the position is rounded up.
+    pub fn seek(&self, position: usize) -> Result<()> {
+        self.command(&format!("seek {}", position.div_ceil(1000)))?;
+        Ok(())
+    }
+
+    /// Tracks of the cmus library, read from its extended playlist format.
+    pub fn library(&self) -> Result<Vec<LibraryTrack>> {
+        let response = self.command("save -l -e -")?;
+        Ok(Cmus::parse_library(&response))
+    }
+
+    fn parse_library(playlist: &str) -> Vec<LibraryTrack> {
+        let mut tracks: Vec<LibraryTrack> = Vec::new();
+
+        for line in playlist.lines() {
+            if let Some(file) = line.strip_prefix("file ") {
+                tracks.push(LibraryTrack {
+                    file: file.trim().to_string(),
+                    title: String::new(),
+                    artist: String::new(),
+                });
+                continue;
+            }
+
+            let Some(track) = tracks.last_mut() else {
+                continue;
+            };
+
+            if let Some(title) = line.strip_prefix("tag title ") {
+                track.title = title.trim().to_string();
+            } else if let Some(artist) = line.strip_prefix("tag artist ") {
+                track.artist = artist.trim().to_string();
+            }
+        }
+
+        tracks
+    }
+
+    fn command(&self, command: &str) -> Result<String> {
         let mut stream = UnixStream::connect(self.socket_path.clone())?;
 
-        stream.write(b"status\n")?;
-        stream.read(&mut response)?;
+        stream.write_all(format!("{}\n", command).as_bytes())?;
+        Cmus::read_response(&mut stream)
+    }
 
-        let mut i = 0;
-        while response[i] != 0 {
-            i = i + 1;
+    /* cmus terminates every reply with an empty line, a reply without
+     * output is the empty line alone */
+    fn read_response(stream: &mut UnixStream) -> Result<String> {
+        let mut response: Vec<u8> = Vec::new();
+        let mut buffer = [0; 2048];
+
+        while !response.ends_with(b"\n\n") && response != b"\n" {
+            let read = stream.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            response.extend_from_slice(&buffer[..read]);
         }
 
-        let response = String::from_utf8_lossy(&response[0..i]).to_string();
-        self.status = response;
-        Ok(())
+        Ok(String::from_utf8_lossy(&response).to_string())
     }
 
     fn parse_status(&self, pattern: &str) -> String {
+        Cmus::status_value(&self.status, pattern)
+    }
+
+    /* The pattern must be a whole key: "tag album" is not "tag albumartist" */
+    fn status_value(status: &str, pattern: &str) -> String {
         let mut value = String::new();
 
-        for line in self.status.lines() {
-            match line.strip_prefix(pattern) {
+        for line in status.lines() {
+            match line
+                .strip_prefix(pattern)
+                .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+            {
                 Some(stripped) => {
                     value = String::from(stripped);
                     break;
@@ -106,3 +209,73 @@ impl Cmus {
         value.trim().to_string()
     }
 }
+
+/// Metadata of the song currently playing on cmus.
+pub fn playing_song() -> Result<PlayerSongInfo> {
+    let mut player = Cmus::new()?;
+    player
+        .update()
+        .map_err(|error| RuntimeError::PlayerError(error.to_string()))?;
+
+    let song = player
+        .playing_song_metadata()
+        .map_err(|_| RuntimeError::NoSongPlaying)?;
+
+    if song.artist.is_empty() && song.title.is_empty() {
+        return Err(RuntimeError::NoSongPlaying);
+    }
+
+    Ok(song)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::cmus::Cmus;
+
+    #[test]
+    fn status_values() {
+        let status = "status playing
+file /music/granite.flac
+duration 214
+position 42
+tag albumartist Various Artists
+tag album Take Me Back To Eden
+tag artist Sleep Token
+tag title Granite
+";
+        assert_eq!(
+            Cmus::status_value(status, "tag album"),
+            "Take Me Back To Eden"
+        );
+        assert_eq!(Cmus::status_value(status, "duration"), "214");
+        assert_eq!(Cmus::status_value(status, "status"), "playing");
+        assert_eq!(Cmus::status_value(status, "tag genre"), "");
+    }
+
+    #[test]
+    fn library_playlist() {
+        let playlist = "file /music/granite.flac
+duration 214
+codec flac
+tag albumartist Various Artists
+tag artist Sleep Token
+tag title Granite
+file /music/untagged.mp3
+duration 180
+file /music/closer.mp3
+tag title One Step Closer
+tag artist Linkin Park
+";
+        let tracks = Cmus::parse_library(playlist);
+
+        assert_eq!(tracks.len(), 3);
+        assert_eq!(tracks[0].file, "/music/granite.flac");
+        assert_eq!(tracks[0].artist, "Sleep Token");
+        assert_eq!(tracks[0].title, "Granite");
+        assert_eq!(tracks[1].artist, "");
+        assert_eq!(tracks[1].title, "");
+        assert_eq!(tracks[2].artist, "Linkin Park");
+        assert_eq!(tracks[2].title, "One Step Closer");
+    }
+}
@@ -0,0 +1,518 @@
+use crate::runtime::bars::Bars;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::gui::Alignment;
+use crate::runtime::gui::theme::{ColorSupport, Theme};
+use crate::runtime::keymap::Keymap;
+use crate::runtime::notifier::NotifyMode;
+use std::env;
+use std::fs::read_to_string;
+use std::sync::OnceLock;
+use std::time::Duration;
+use toml::{Table, Value};
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+/// Settings of ciryl, read from the configuration file and overridden by the
+/// environment variables.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Config {
+    /// Path of the cmus socket, `$XDG_RUNTIME_DIR/cmus-socket` when unset.
+    pub socket: Option<String>,
+    /// Directories where the lyrics are looked up, in order.
+    pub lyrics_dirs: Vec<String>,
+    pub theme: String,
+    pub alignment: Alignment,
+    /// Milliseconds added to the position of every song.
+    pub global_offset: isize,
+    pub header: String,
+    pub footer: String,
+    /// Rows kept above the lyric, the header being on the first one.
+    pub margin_top: usize,
+    /// Rows kept below the lyric, the footer being on the last one.
+    pub margin_bottom: usize,
+    /// Keybindings in the format of `Keymap::parse`.
+    pub keybindings: String,
+    /// Interval between two updates of the TUI.
+    pub tick: Duration,
+    /// Duration of the scrolling between verses, zero to jump at once.
+    pub animation: Duration,
+    /// Time without browsing after which the song is followed again.
+    pub browse_timeout: Duration,
+    /// Milliseconds the offsets are shifted by on each key press.
+    pub offset_step: isize,
+    /// Events desktop notifications are sent on.
+    pub notify: NotifyMode,
+    /// Milliseconds before a notification expires, -1 for the server default.
+    pub notify_timeout: i32,
+}
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+/// Configuration in use, the default one until `set` is called.
+pub fn get() -> &'static Config {
+    CONFIG.get_or_init(Config::default)
+}
+
+/// Use `config` for the rest of the program; only the first call has effect.
+pub fn set(config: Config) {
+    let _ = CONFIG.set(config);
+}
+
+impl Default for Config {
+    fn default() -> Config {
+        Config {
+            socket: None,
+            lyrics_dirs: Vec::new(),
+            theme: "default".to_string(),
+            alignment: Alignment::Center,
+            global_offset: 0,
+            header: Bars::HEADER.to_string(),
+            footer: Bars::FOOTER.to_string(),
+            margin_top: 2,
+            margin_bottom: 2,
+            keybindings: String::new(),
+            tick: Duration::from_millis(100),
+            animation: Duration::ZERO,
+            browse_timeout: Duration::from_secs(5),
+            offset_step: 100,
+            notify: NotifyMode::Off,
+            notify_timeout: -1,
+        }
+    }
+}
+
+impl Config {
+    const KEYS: [(&str, &[&str]); 6] = [
+        ("player", &["backend", "socket"]),
+        ("lyrics", &["dirs"]),
+        (
+            "display",
+            &[
+                "theme",
+                "alignment",
+                "offset",
+                "header",
+                "footer",
+                "margin_top",
+                "margin_bottom",
+            ],
+        ),
+        ("keybindings", &[]),
+        (
+            "timing",
+            &["tick", "animation", "browse_timeout", "offset_step"],
+        ),
+        ("notifications", &["mode", "timeout"]),
+    ];
+
+    /// Default path of the configuration file,
+    /// `$XDG_CONFIG_HOME/ciryl/config.toml` or `~/.config/ciryl/config.toml`.
+    pub fn path() -> Option<String> {
+        let config_home = match env::var("XDG_CONFIG_HOME") {
+            Ok(path) if !path.is_empty() => path,
+            _ => format!("{}/.config", env::var("HOME").ok()?),
+        };
+        Some(format!("{}/ciryl/config.toml", config_home))
+    }
+
+    /// Configuration of the file at `path`, or at the default path when
+    /// `None`, overridden by the environment variables. A missing file at the
+    /// default path gives the default configuration.
+    pub fn load(path: Option<&str>) -> Result<Config> {
+        let mut config = match path {
+            Some(path) => Config::parse(&read_config(path)?, path)?,
+            None => match Config::path() {
+                Some(path) if std::path::Path::new(&path).exists() => {
+                    Config::parse(&read_config(&path)?, &path)?
+                }
+                _ => Config::default(),
+            },
+        };
+
+        config.apply_env(&|name| env::var(name).ok())?;
+        Ok(config)
+    }
+
+    /// Configuration given by the TOML `content` of the file at `path`.
+    pub fn parse(content: &str, path: &str) -> Result<Config> {
+        let error = |message: String| RuntimeError::ParseError(format!("{}: {}", path, message));
+        let table: Table = content
+            .parse()
+            .map_err(|parse_error: toml::de::Error| error(parse_error.to_string()))?;
+
+        Config::from_table(&table).map_err(error)
+    }
+
+    fn from_table(table: &Table) -> std::result::Result<Config, String> {
+        let mut config = Config::default();
+
+        for (name, value) in table {
+            let Some((_, keys)) = Config::KEYS.iter().find(|(section, _)| section == name) else {
+                return Err(format!("unknown section '{}'", name));
+            };
+            let Value::Table(section) = value else {
+                return Err(format!("'{}' must be a section", name));
+            };
+            if let Some(key) = section
+                .keys()
+                .find(|key| !keys.is_empty() && !keys.contains(&key.as_str()))
+            {
+                return Err(format!("unknown key '{}.{}'", name, key));
+            }
+        }
+
+        let section = |name: &str| Section {
+            name: name.to_string(),
+            table: match table.get(name) {
+                Some(Value::Table(table)) => Some(table),
+                _ => None,
+            },
+        };
+
+        let player = section("player");
+        if let Some(backend) = player.string("backend")?
+            && backend != "cmus"
+        {
+            return Err(format!(
+                "player.backend: unsupported player '{}', only cmus is supported",
+                backend
+            ));
+        }
+        config.socket = player.string("socket")?.map(|path| expand_home(&path));
+
+        if let Some(dirs) = section("lyrics").strings("dirs")? {
+            config.lyrics_dirs = dirs.iter().map(|dir| expand_home(dir)).collect();
+        }
+
+        let display = section("display");
+        if let Some(theme) = display.string("theme")? {
+            config.theme = theme;
+        }
+        if let Some(alignment) = display.string("alignment")? {
+            config.alignment = Alignment::parse(&alignment).ok_or(format!(
+                "display.alignment: expected left, center or right, found '{}'",
+                alignment
+            ))?;
+        }
+        if let Some(offset) = display.integer("offset")? {
+            config.global_offset = offset as isize;
+        }
+        if let Some(header) = display.string("header")? {
+            config.header = header;
+        }
+        if let Some(footer) = display.string("footer")? {
+            config.footer = footer;
+        }
+        /* The margins hold the bars: the header above the lyric, the status
+         * row and the footer below it */
+        let rows = |key: &str, bars: i64| -> std::result::Result<Option<usize>, String> {
+            match display.integer(key)? {
+                Some(rows) if rows < bars => Err(format!(
+                    "display.{}: must be at least {}, the rows of the bars",
+                    key, bars
+                )),
+                rows => Ok(rows.map(|rows| rows as usize)),
+            }
+        };
+        if let Some(margin) = rows("margin_top", 1)? {
+            config.margin_top = margin;
+        }
+        if let Some(margin) = rows("margin_bottom", 2)? {
+            config.margin_bottom = margin;
+        }
+
+        /* Bindings of the table turned into the format of the environment */
+        let keybindings = section("keybindings");
+        let mut bindings = Vec::new();
+        for action in keybindings.table.into_iter().flat_map(|table| table.keys()) {
+            let keys = keybindings.strings(action)?.unwrap_or_default();
+            bindings.push(format!("{}={}", action, keys.join(" ")));
+        }
+        config.keybindings = bindings.join(";");
+
+        let timing = section("timing");
+        let milliseconds = |key: &str| -> std::result::Result<Option<Duration>, String> {
+            match timing.integer(key)? {
+                Some(ms) if ms < 0 => Err(format!("timing.{}: must not be negative", key)),
+                ms => Ok(ms.map(|ms| Duration::from_millis(ms as u64))),
+            }
+        };
+        if let Some(tick) = milliseconds("tick")? {
+            if tick.is_zero() {
+                return Err("timing.tick: must be greater than zero".to_string());
+            }
+            config.tick = tick;
+        }
+        if let Some(animation) = milliseconds("animation")? {
+            config.animation = animation;
+        }
+        if let Some(timeout) = milliseconds("browse_timeout")? {
+            config.browse_timeout = timeout;
+        }
+        if let Some(step) = milliseconds("offset_step")? {
+            config.offset_step = step.as_millis() as isize;
+        }
+
+        let notifications = section("notifications");
+        if let Some(mode) = notifications.string("mode")? {
+            config.notify = NotifyMode::parse(&mode).ok_or(format!(
+                "notifications.mode: expected off, song or verse, found '{}'",
+                mode
+            ))?;
+        }
+        if let Some(timeout) = notifications.integer("timeout")? {
+            config.notify_timeout = i32::try_from(timeout)
+                .ok()
+                .filter(|timeout| *timeout >= -1)
+                .ok_or("notifications.timeout: must be -1 or more".to_string())?;
+        }
+
+        config.check()?;
+        Ok(config)
+    }
+
+    /// Override the settings with the environment variables read by `var`.
+    fn apply_env(&mut self, var: &dyn Fn(&str) -> Option<String>) -> Result<()> {
+        let error = |name: &str, message: String| {
+            RuntimeError::ParseError(format!("{}: {}", name, message))
+        };
+
+        if let Some(dir) = var("LYRICS_DIR") {
+            self.lyrics_dirs = vec![dir];
+        }
+        if self.socket.is_none()
+            && let Some(runtime_dir) = var("XDG_RUNTIME_DIR")
+        {
+            self.socket = Some(format!("{}/cmus-socket", runtime_dir));
+        }
+        if let Some(theme) = var("CIRYL_THEME") {
+            Theme::parse(&theme, ColorSupport::None).map_err(|e| error("CIRYL_THEME", e))?;
+            self.theme = theme;
+        }
+        if let Some(header) = var("CIRYL_HEADER") {
+            self.header = header;
+        }
+        if let Some(footer) = var("CIRYL_FOOTER") {
+            self.footer = footer;
+        }
+        if let Some(keys) = var("CIRYL_KEYS") {
+            Keymap::parse(&keys).map_err(|e| error("CIRYL_KEYS", e))?;
+            self.keybindings = format!("{};{}", self.keybindings, keys);
+        }
+        if let Some(animation) = var("CIRYL_ANIMATION") {
+            let ms: u64 = animation.trim().parse().map_err(|_| {
+                error(
+                    "CIRYL_ANIMATION",
+                    format!("invalid duration '{}'", animation),
+                )
+            })?;
+            self.animation = Duration::from_millis(ms);
+        }
+
+        Ok(())
+    }
+
+    /// Check the settings parsed by other modules.
+    fn check(&self) -> std::result::Result<(), String> {
+        Theme::parse(&self.theme, ColorSupport::None)
+            .map_err(|error| format!("display.theme: {}", error))?;
+        Keymap::parse(&self.keybindings).map_err(|error| format!("keybindings: {}", error))?;
+        Ok(())
+    }
+}
+
+/* Section of the configuration file, possibly missing */
+struct Section<'a> {
+    name: String,
+    table: Option<&'a Table>,
+}
+
+impl Section<'_> {
+    fn get(&self, key: &str) -> Option<&Value> {
+        self.table.and_then(|table| table.get(key))
+    }
+
+    fn invalid(&self, key: &str, expected: &str, value: &Value) -> String {
+        format!(
+            "{}.{}: expected {}, found {}",
+            self.name,
+            key,
+            expected,
+            value.type_str()
+        )
+    }
+
+    fn string(&self, key: &str) -> std::result::Result<Option<String>, String> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(value)) => Ok(Some(value.clone())),
+            Some(value) => Err(self.invalid(key, "a string", value)),
+        }
+    }
+
+    fn integer(&self, key: &str) -> std::result::Result<Option<i64>, String> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::Integer(value)) => Ok(Some(*value)),
+            Some(value) => Err(self.invalid(key, "an integer", value)),
+        }
+    }
+
+    /// A list of strings, a single string being a list of its words.
+    fn strings(&self, key: &str) -> std::result::Result<Option<Vec<String>>, String> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(Value::String(value)) => {
+                Ok(Some(value.split_whitespace().map(str::to_string).collect()))
+            }
+            Some(Value::Array(values)) => values
+                .iter()
+                .map(|value| match value {
+                    Value::String(value) => Ok(value.clone()),
+                    value => Err(self.invalid(key, "a list of strings", value)),
+                })
+                .collect::<std::result::Result<Vec<String>, String>>()
+                .map(Some),
+            Some(value) => Err(self.invalid(key, "a list of strings", value)),
+        }
+    }
+}
+
+fn read_config(path: &str) -> Result<String> {
+    read_to_string(path).map_err(|error| RuntimeError::ParseError(format!("{}: {}", path, error)))
+}
+
+/* Replace a leading "~/" with the home directory */
+fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home, rest),
+        _ => path.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::config::Config;
+    use crate::runtime::gui::Alignment;
+    use crate::runtime::notifier::NotifyMode;
+    use std::time::Duration;
+
+    const CONFIG: &str = r#"
+[player]
+backend = "cmus"
+socket = "/tmp/cmus-socket"
+
+[lyrics]
+dirs = ["/srv/lyrics", "/home/me/lyrics"]
+
+[display]
+theme = "dim;current=bold yellow"
+alignment = "left"
+offset = -200
+margin_top = 3
+
+[keybindings]
+quit = ["ctrl-q", "esc"]
+follow = "f space"
+
+[timing]
+tick = 50
+animation = 300
+
+[notifications]
+mode = "song"
+"#;
+
+    #[test]
+    fn parse_config() {
+        let config = Config::parse(CONFIG, "config.toml").unwrap();
+
+        assert_eq!(config.socket.as_deref(), Some("/tmp/cmus-socket"));
+        assert_eq!(config.lyrics_dirs, vec!["/srv/lyrics", "/home/me/lyrics"]);
+        assert_eq!(config.theme, "dim;current=bold yellow");
+        assert_eq!(config.alignment, Alignment::Left);
+        assert_eq!(config.global_offset, -200);
+        assert_eq!(config.margin_top, 3);
+        assert_eq!(config.margin_bottom, 2);
+        assert_eq!(config.keybindings, "follow=f space;quit=ctrl-q esc");
+        assert_eq!(config.tick, Duration::from_millis(50));
+        assert_eq!(config.animation, Duration::from_millis(300));
+        assert_eq!(config.browse_timeout, Duration::from_secs(5));
+        assert_eq!(config.header, Config::default().header);
+        assert_eq!(config.notify, NotifyMode::Song);
+        assert_eq!(config.notify_timeout, -1);
+
+        assert_eq!(Config::parse("", "config.toml"), Ok(Config::default()));
+    }
+
+    #[test]
+    fn invalid_config() {
+        let error = |content: &str| {
+            Config::parse(content, "config.toml")
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error("[display]\nalignment = \"middle\""),
+            "parse error: config.toml: display.alignment: expected left, center or right, found 'middle'"
+        );
+        assert_eq!(
+            error("[display]\nmargin_top = 0"),
+            "parse error: config.toml: display.margin_top: must be at least 1, the rows of the bars"
+        );
+        assert_eq!(
+            error("[display]\nmargin_bottom = 1"),
+            "parse error: config.toml: display.margin_bottom: must be at least 2, the rows of the bars"
+        );
+        assert_eq!(
+            error("[timing]\ntick = \"fast\""),
+            "parse error: config.toml: timing.tick: expected an integer, found string"
+        );
+        assert_eq!(
+            error("[player]\nbackend = \"mpd\""),
+            "parse error: config.toml: player.backend: unsupported player 'mpd', only cmus is supported"
+        );
+        assert_eq!(
+            error("[display]\ncolour = \"red\""),
+            "parse error: config.toml: unknown key 'display.colour'"
+        );
+        assert_eq!(
+            error("[keybindings]\njump = \"j\""),
+            "parse error: config.toml: keybindings: unknown action 'jump'"
+        );
+        assert_eq!(
+            error("[display]\ntheme = \"neon\""),
+            "parse error: config.toml: display.theme: unknown theme 'neon', available themes: default, dim, ocean"
+        );
+        assert_eq!(
+            error("[notifications]\nmode = \"always\""),
+            "parse error: config.toml: notifications.mode: expected off, song or verse, found 'always'"
+        );
+        assert!(error("[display").starts_with("parse error: config.toml: TOML parse error"));
+    }
+
+    #[test]
+    fn environment_overrides() {
+        let mut config = Config::parse(CONFIG, "config.toml").unwrap();
+        let env = |name: &str| match name {
+            "LYRICS_DIR" => Some("/tmp/lyrics".to_string()),
+            "XDG_RUNTIME_DIR" => Some("/run/user/1000".to_string()),
+            "CIRYL_KEYS" => Some("help=h".to_string()),
+            _ => None,
+        };
+        config.apply_env(&env).unwrap();
+
+        assert_eq!(config.lyrics_dirs, vec!["/tmp/lyrics"]);
+        assert_eq!(config.socket.as_deref(), Some("/tmp/cmus-socket"));
+        assert_eq!(config.keybindings, "follow=f space;quit=ctrl-q esc;help=h");
+
+        let mut config = Config::default();
+        config.apply_env(&env).unwrap();
+        assert_eq!(config.socket.as_deref(), Some("/run/user/1000/cmus-socket"));
+
+        let env = |name: &str| (name == "CIRYL_ANIMATION").then(|| "soon".to_string());
+        assert!(Config::default().apply_env(&env).is_err());
+    }
+}
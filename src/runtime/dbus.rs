@@ -0,0 +1,648 @@
+use std::env;
+use std::io::{Error, ErrorKind, Read, Result, Write};
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+/// Value of the D-Bus type system, limited to the types ciryl sends and
+/// receives.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Value {
+    Byte(u8),
+    Uint32(u32),
+    Int32(i32),
+    Str(String),
+    ObjectPath(String),
+    Signature(String),
+    StrArray(Vec<String>),
+    /// A dictionary of strings to variants, `a{sv}`.
+    Dict(Vec<(String, Value)>),
+}
+
+impl Value {
+    fn signature(&self) -> &'static str {
+        match self {
+            Value::Byte(_) => "y",
+            Value::Uint32(_) => "u",
+            Value::Int32(_) => "i",
+            Value::Str(_) => "s",
+            Value::ObjectPath(_) => "o",
+            Value::Signature(_) => "g",
+            Value::StrArray(_) => "as",
+            Value::Dict(_) => "a{sv}",
+        }
+    }
+}
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+const FIELD_SIGNATURE: u8 = 8;
+
+/* Maximum lengths of a message and of an array, per the specification */
+const MAX_MESSAGE_LENGTH: usize = 1 << 27;
+const MAX_ARRAY_LENGTH: usize = 1 << 26;
+
+/// Message of the D-Bus wire protocol.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Message {
+    pub kind: u8,
+    pub serial: u32,
+    /// Header fields, as code and value.
+    pub fields: Vec<(u8, Value)>,
+    pub body: Vec<Value>,
+}
+
+impl Message {
+    pub fn method_call(
+        serial: u32,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Value>,
+    ) -> Message {
+        Message {
+            kind: METHOD_CALL,
+            serial,
+            fields: vec![
+                (FIELD_PATH, Value::ObjectPath(path.to_string())),
+                (FIELD_INTERFACE, Value::Str(interface.to_string())),
+                (FIELD_MEMBER, Value::Str(member.to_string())),
+                (FIELD_DESTINATION, Value::Str(destination.to_string())),
+            ],
+            body,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn method_return(serial: u32, reply_serial: u32, body: Vec<Value>) -> Message {
+        Message {
+            kind: METHOD_RETURN,
+            serial,
+            fields: vec![(FIELD_REPLY_SERIAL, Value::Uint32(reply_serial))],
+            body,
+        }
+    }
+
+    pub fn field(&self, code: u8) -> Option<&Value> {
+        self.fields
+            .iter()
+            .find(|(field, _)| *field == code)
+            .map(|(_, value)| value)
+    }
+
+    #[cfg(test)]
+    pub fn member(&self) -> Option<&str> {
+        match self.field(FIELD_MEMBER) {
+            Some(Value::Str(member)) => Some(member),
+            _ => None,
+        }
+    }
+
+    /// Message in little endian wire format.
+    pub fn encode(&self) -> Vec<u8> {
+        /* The body starts 8-aligned, so it is aligned on its own */
+        let mut body = Writer::default();
+        for value in &self.body {
+            body.write(value);
+        }
+        let signature: String = self.body.iter().map(Value::signature).collect();
+
+        let mut fields = self.fields.clone();
+        if !signature.is_empty() {
+            fields.push((FIELD_SIGNATURE, Value::Signature(signature)));
+        }
+
+        let mut message = Writer::default();
+        message.buffer.extend([b'l', self.kind, 0, 1]);
+        message.write(&Value::Uint32(body.buffer.len() as u32));
+        message.write(&Value::Uint32(self.serial));
+        message.array(8, |writer| {
+            for (code, value) in &fields {
+                writer.align(8);
+                writer.write(&Value::Byte(*code));
+                writer.write(&Value::Signature(value.signature().to_string()));
+                writer.write(value);
+            }
+        });
+        message.align(8);
+        message.buffer.extend(body.buffer);
+
+        message.buffer
+    }
+
+    /// Read a whole message from `stream`.
+    pub fn read(stream: &mut impl Read) -> Result<Message> {
+        let mut buffer = vec![0; 16];
+        stream.read_exact(&mut buffer)?;
+
+        let big_endian = match buffer[0] {
+            b'l' => false,
+            b'B' => true,
+            _ => return Err(invalid("unknown endianness")),
+        };
+        let number = |bytes: &[u8]| {
+            let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+            match big_endian {
+                true => u32::from_be_bytes(bytes) as usize,
+                false => u32::from_le_bytes(bytes) as usize,
+            }
+        };
+        let body_length = number(&buffer[4..8]);
+        let fields_length = number(&buffer[12..16]);
+
+        /* Checked before allocating the message */
+        if fields_length > MAX_ARRAY_LENGTH {
+            return Err(invalid("header fields too long"));
+        }
+        if 16 + fields_length + body_length > MAX_MESSAGE_LENGTH {
+            return Err(invalid("message too long"));
+        }
+
+        let header_length = (16 + fields_length).next_multiple_of(8);
+        buffer.resize(header_length + body_length, 0);
+        stream.read_exact(&mut buffer[16..])?;
+
+        let mut reader = Reader {
+            buffer: &buffer,
+            position: 4,
+            big_endian,
+        };
+        let kind = buffer[1];
+        let _ = reader.number()?;
+        let serial = reader.number()?;
+
+        let mut fields = Vec::new();
+        let end = reader.array_start(8)?;
+        while reader.position < end {
+            reader.align(8);
+            let code = reader.byte()?;
+            let signature = reader.signature()?;
+            fields.push((code, reader.read(&signature)?));
+        }
+        reader.align(8);
+
+        let signature = match fields.iter().find(|(code, _)| *code == FIELD_SIGNATURE) {
+            Some((_, Value::Signature(signature))) => signature.clone(),
+            _ => String::new(),
+        };
+        let body = split_signature(&signature)?
+            .iter()
+            .map(|signature| reader.read(signature))
+            .collect::<Result<Vec<Value>>>()?;
+
+        Ok(Message {
+            kind,
+            serial,
+            fields,
+            body,
+        })
+    }
+}
+
+fn invalid(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("D-Bus: {}", message))
+}
+
+/// Complete types of `signature`, e.g. "sa{sv}i" gives "s", "a{sv}" and "i".
+fn split_signature(signature: &str) -> Result<Vec<String>> {
+    let mut types = Vec::new();
+    let mut current = String::new();
+    let mut depth = 0;
+
+    for c in signature.chars() {
+        current.push(c);
+        match c {
+            'a' => continue,
+            '{' | '(' => depth += 1,
+            '}' | ')' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            types.push(std::mem::take(&mut current));
+        }
+    }
+
+    match current.is_empty() && depth == 0 {
+        true => Ok(types),
+        false => Err(invalid("malformed signature")),
+    }
+}
+
+#[derive(Default)]
+struct Writer {
+    buffer: Vec<u8>,
+}
+
+impl Writer {
+    fn align(&mut self, alignment: usize) {
+        let length = self.buffer.len().next_multiple_of(alignment);
+        self.buffer.resize(length, 0);
+    }
+
+    fn write(&mut self, value: &Value) {
+        match value {
+            Value::Byte(byte) => self.buffer.push(*byte),
+            Value::Uint32(number) => {
+                self.align(4);
+                self.buffer.extend(number.to_le_bytes());
+            }
+            Value::Int32(number) => {
+                self.align(4);
+                self.buffer.extend(number.to_le_bytes());
+            }
+            Value::Str(string) | Value::ObjectPath(string) => {
+                self.write(&Value::Uint32(string.len() as u32));
+                self.buffer.extend(string.as_bytes());
+                self.buffer.push(0);
+            }
+            Value::Signature(signature) => {
+                self.buffer.push(signature.len() as u8);
+                self.buffer.extend(signature.as_bytes());
+                self.buffer.push(0);
+            }
+            Value::StrArray(strings) => self.array(4, |writer| {
+                for string in strings {
+                    writer.write(&Value::Str(string.clone()));
+                }
+            }),
+            Value::Dict(entries) => self.array(8, |writer| {
+                for (key, value) in entries {
+                    writer.align(8);
+                    writer.write(&Value::Str(key.clone()));
+                    writer.write(&Value::Signature(value.signature().to_string()));
+                    writer.write(value);
+                }
+            }),
+        }
+    }
+
+    /// Array of elements aligned to `alignment`, written by `elements`.
+    fn array(&mut self, alignment: usize, elements: impl FnOnce(&mut Writer)) {
+        self.align(4);
+        let length_at = self.buffer.len();
+        self.buffer.extend([0; 4]);
+        self.align(alignment);
+
+        let start = self.buffer.len();
+        elements(self);
+        let length = (self.buffer.len() - start) as u32;
+        self.buffer[length_at..length_at + 4].copy_from_slice(&length.to_le_bytes());
+    }
+}
+
+struct Reader<'a> {
+    buffer: &'a [u8],
+    position: usize,
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn align(&mut self, alignment: usize) {
+        self.position = self.position.next_multiple_of(alignment);
+    }
+
+    fn take(&mut self, length: usize) -> Result<&[u8]> {
+        let bytes = self
+            .buffer
+            .get(self.position..self.position + length)
+            .ok_or(invalid("message too short"))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn number(&mut self) -> Result<u32> {
+        self.align(4);
+        let bytes = self.take(4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Ok(match self.big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn string(&mut self, length: usize) -> Result<String> {
+        let bytes = self.take(length + 1)?;
+        String::from_utf8(bytes[..length].to_vec()).map_err(|_| invalid("invalid string"))
+    }
+
+    /// A string or object path, after its 32-bit length.
+    fn text(&mut self) -> Result<String> {
+        let length = self.number()? as usize;
+        self.string(length)
+    }
+
+    /// A signature, after its 8-bit length.
+    fn signature(&mut self) -> Result<String> {
+        let length = self.byte()? as usize;
+        self.string(length)
+    }
+
+    /// Read the length of an array, returning the position where it ends.
+    fn array_start(&mut self, alignment: usize) -> Result<usize> {
+        let length = self.number()? as usize;
+        self.align(alignment);
+        Ok(self.position + length)
+    }
+
+    fn read(&mut self, signature: &str) -> Result<Value> {
+        let value = match signature {
+            "y" => Value::Byte(self.byte()?),
+            "u" => Value::Uint32(self.number()?),
+            "i" => Value::Int32(self.number()? as i32),
+            "s" => Value::Str(self.text()?),
+            "o" => Value::ObjectPath(self.text()?),
+            "g" => Value::Signature(self.signature()?),
+            "as" => {
+                let mut strings = Vec::new();
+                let end = self.array_start(4)?;
+                while self.position < end {
+                    strings.push(self.text()?);
+                }
+                Value::StrArray(strings)
+            }
+            "a{sv}" => {
+                let mut entries = Vec::new();
+                let end = self.array_start(8)?;
+                while self.position < end {
+                    self.align(8);
+                    let key = self.text()?;
+                    let signature = self.signature()?;
+                    entries.push((key, self.read(&signature)?));
+                }
+                Value::Dict(entries)
+            }
+            signature => return Err(invalid(&format!("unsupported type '{}'", signature))),
+        };
+
+        Ok(value)
+    }
+}
+
+/// Connection to a message bus.
+pub struct Bus {
+    stream: UnixStream,
+    serial: u32,
+}
+
+impl Bus {
+    const TIMEOUT: Duration = Duration::from_secs(2);
+
+    /// Connection to the session bus of `DBUS_SESSION_BUS_ADDRESS`, or of
+    /// `$XDG_RUNTIME_DIR/bus` when unset.
+    pub fn session() -> Result<Bus> {
+        let address = match env::var("DBUS_SESSION_BUS_ADDRESS") {
+            Ok(address) => address,
+            Err(_) => match env::var("XDG_RUNTIME_DIR") {
+                Ok(runtime_dir) => format!("unix:path={}/bus", runtime_dir),
+                Err(_) => return Err(invalid("session bus address not set")),
+            },
+        };
+
+        Bus::connect(&address)
+    }
+
+    /// Connection to the first unix socket path of the bus `address`.
+    pub fn connect(address: &str) -> Result<Bus> {
+        let path = address
+            .split(';')
+            .filter_map(|transport| transport.strip_prefix("unix:"))
+            .flat_map(|options| options.split(','))
+            .find_map(|option| option.strip_prefix("path="))
+            .ok_or(invalid(&format!("unsupported bus address '{}'", address)))?;
+
+        let stream = UnixStream::connect(path)?;
+        stream.set_read_timeout(Some(Bus::TIMEOUT))?;
+        stream.set_write_timeout(Some(Bus::TIMEOUT))?;
+
+        let mut bus = Bus { stream, serial: 0 };
+        bus.authenticate()?;
+        bus.call(
+            "org.freedesktop.DBus",
+            "/org/freedesktop/DBus",
+            "org.freedesktop.DBus",
+            "Hello",
+            Vec::new(),
+        )?;
+
+        Ok(bus)
+    }
+
+    /// Authenticate as the user owning the process.
+    fn authenticate(&mut self) -> Result<()> {
+        let uid = std::fs::metadata("/proc/self")?.uid().to_string();
+        let hex_uid: String = uid.bytes().map(|byte| format!("{:02x}", byte)).collect();
+
+        self.stream.write_all(b"\0")?;
+        self.stream
+            .write_all(format!("AUTH EXTERNAL {}\r\n", hex_uid).as_bytes())?;
+        let reply = self.read_line()?;
+        if !reply.starts_with("OK ") {
+            return Err(invalid(&format!("authentication rejected: {}", reply)));
+        }
+
+        self.stream.write_all(b"BEGIN\r\n")?;
+        Ok(())
+    }
+
+    /* Line of the authentication protocol, read byte by byte so none of the
+     * following messages is consumed */
+    fn read_line(&mut self) -> Result<String> {
+        let mut line = Vec::new();
+        let mut byte = [0];
+        while !line.ends_with(b"\r\n") {
+            self.stream.read_exact(&mut byte)?;
+            line.push(byte[0]);
+        }
+
+        Ok(String::from_utf8_lossy(&line).trim_end().to_string())
+    }
+
+    /// Call the method `member` and wait for its return values.
+    pub fn call(
+        &mut self,
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Value>,
+    ) -> Result<Vec<Value>> {
+        self.serial += 1;
+        let call = Message::method_call(self.serial, destination, path, interface, member, body);
+        self.stream.write_all(&call.encode())?;
+
+        /* Skip the signals and replies to other calls */
+        loop {
+            let message = Message::read(&mut self.stream)?;
+            if message.field(FIELD_REPLY_SERIAL) != Some(&Value::Uint32(self.serial)) {
+                continue;
+            }
+
+            match message.kind {
+                METHOD_RETURN => return Ok(message.body),
+                ERROR => {
+                    let name = match message.field(FIELD_ERROR_NAME) {
+                        Some(Value::Str(name)) => name.as_str(),
+                        _ => "unknown error",
+                    };
+                    let text = match message.body.first() {
+                        Some(Value::Str(text)) => text.as_str(),
+                        _ => "",
+                    };
+                    return Err(Error::other(format!("{}: {}", name, text)));
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::dbus::{
+        ERROR, FIELD_ERROR_NAME, FIELD_MEMBER, FIELD_REPLY_SERIAL, METHOD_RETURN, Message, Value,
+        split_signature,
+    };
+    use std::io::ErrorKind;
+
+    /* Reply of dbus-daemon to Hello */
+    const HELLO_REPLY: &[u8] =
+        b"l\x02\x01\x01\x09\x00\x00\x00\x01\x00\x00\x00=\x00\x00\x00\x06\x01s\x00\x04\
+          \x00\x00\x00:1.5\x00\x00\x00\x00\x05\x01u\x00\x01\x00\x00\x00\x08\x01g\x00\
+          \x01s\x00\x00\x07\x01s\x00\x14\x00\x00\x00org.freedesktop.DBus\x00\x00\x00\
+          \x00\x04\x00\x00\x00:1.5\x00";
+
+    /* Signal of dbus-daemon following the reply to Hello */
+    const NAME_ACQUIRED: &[u8] =
+        b"l\x04\x01\x01\x09\x00\x00\x00\x02\x00\x00\x00\x8d\x00\x00\x00\x01\x01o\x00\
+          \x15\x00\x00\x00/org/freedesktop/DBus\x00\x00\x00\x02\x01s\x00\x14\x00\x00\
+          \x00org.freedesktop.DBus\x00\x00\x00\x00\x03\x01s\x00\x0c\x00\x00\x00NameAcq\
+          uired\x00\x00\x00\x00\x06\x01s\x00\x04\x00\x00\x00:1.5\x00\x00\x00\x00\x08\
+          \x01g\x00\x01s\x00\x00\x07\x01s\x00\x14\x00\x00\x00org.freedesktop.DBus\x00\
+          \x00\x00\x00\x04\x00\x00\x00:1.5\x00";
+
+    /* Error replied by dbus-daemon to a call to a missing notification server */
+    const SERVICE_UNKNOWN: &[u8] =
+        b"l\x03\x01\x01R\x00\x00\x00\x03\x00\x00\x00u\x00\x00\x00\x06\x01s\x00\x04\x00\
+          \x00\x00:1.5\x00\x00\x00\x00\x04\x01s\x00)\x00\x00\x00org.freedesktop.DBus.E\
+          rror.ServiceUnknown\x00\x00\x00\x00\x00\x00\x00\x05\x01u\x00\x02\x00\x00\x00\
+          \x08\x01g\x00\x01s\x00\x00\x07\x01s\x00\x14\x00\x00\x00org.freedesktop.DBus\
+          \x00\x00\x00\x00M\x00\x00\x00The name org.freedesktop.Notifications was not \
+          provided by any .service files\x00";
+
+    #[test]
+    fn signature_types() {
+        assert_eq!(
+            split_signature("susssasa{sv}i").unwrap(),
+            vec!["s", "u", "s", "s", "s", "as", "a{sv}", "i"]
+        );
+        assert!(split_signature("a{sv").is_err());
+    }
+
+    #[test]
+    fn encode_and_read_messages() {
+        let call = Message::method_call(
+            7,
+            "org.freedesktop.Notifications",
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications",
+            "Notify",
+            vec![
+                Value::Str("ciryl".to_string()),
+                Value::Uint32(3),
+                Value::StrArray(vec!["a".to_string(), "b".to_string()]),
+                Value::Dict(vec![("urgency".to_string(), Value::Byte(1))]),
+                Value::Int32(-1),
+            ],
+        );
+
+        let encoded = call.encode();
+        assert_eq!(&encoded[..4], b"l\x01\x00\x01");
+        /* The body starts 8-aligned */
+        assert_eq!((encoded.len() - call_body_length(&encoded)) % 8, 0);
+
+        let read = Message::read(&mut encoded.as_slice()).unwrap();
+        assert_eq!(read.serial, 7);
+        assert_eq!(read.member(), Some("Notify"));
+        assert_eq!(read.body, call.body);
+    }
+
+    #[test]
+    fn read_bus_messages() {
+        let hello = Message::read(&mut &HELLO_REPLY[..]).unwrap();
+        assert_eq!(hello.kind, METHOD_RETURN);
+        assert_eq!(hello.field(FIELD_REPLY_SERIAL), Some(&Value::Uint32(1)));
+        assert_eq!(hello.body, vec![Value::Str(":1.5".to_string())]);
+
+        let acquired = Message::read(&mut &NAME_ACQUIRED[..]).unwrap();
+        assert_eq!(acquired.kind, 4);
+        assert_eq!(
+            acquired.field(FIELD_MEMBER),
+            Some(&Value::Str("NameAcquired".to_string()))
+        );
+        assert_eq!(acquired.body, vec![Value::Str(":1.5".to_string())]);
+
+        let error = Message::read(&mut &SERVICE_UNKNOWN[..]).unwrap();
+        assert_eq!(error.kind, ERROR);
+        assert_eq!(error.field(FIELD_REPLY_SERIAL), Some(&Value::Uint32(2)));
+        assert_eq!(
+            error.field(FIELD_ERROR_NAME),
+            Some(&Value::Str(
+                "org.freedesktop.DBus.Error.ServiceUnknown".to_string()
+            ))
+        );
+        assert_eq!(
+            error.body,
+            vec![Value::Str(
+                "The name org.freedesktop.Notifications was not provided by any .service files"
+                    .to_string()
+            )]
+        );
+
+        /* Messages read one after the other from a stream */
+        let stream = [HELLO_REPLY, NAME_ACQUIRED].concat();
+        let mut stream = stream.as_slice();
+        assert_eq!(Message::read(&mut stream).unwrap(), hello);
+        assert_eq!(Message::read(&mut stream).unwrap(), acquired);
+        assert!(stream.is_empty());
+    }
+
+    #[test]
+    fn malformed_messages() {
+        let header = |body_length: u32, fields_length: u32| {
+            let mut header = b"l\x02\x00\x01".to_vec();
+            header.extend(body_length.to_le_bytes());
+            header.extend(1u32.to_le_bytes());
+            header.extend(fields_length.to_le_bytes());
+            header
+        };
+
+        for (body_length, fields_length) in [(0, 1 << 27), (1 << 27, 8), (u32::MAX, u32::MAX)] {
+            let error = Message::read(&mut header(body_length, fields_length).as_slice());
+            assert_eq!(error.unwrap_err().kind(), ErrorKind::InvalidData);
+        }
+
+        /* A header field of a type the reader doesn't know */
+        let mut message = header(0, 4);
+        message.extend(b"\x08\x01x\x00\x00\x00\x00\x00");
+        let error = Message::read(&mut message.as_slice());
+        assert_eq!(error.unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    fn call_body_length(message: &[u8]) -> usize {
+        u32::from_le_bytes([message[4], message[5], message[6], message[7]]) as usize
+    }
+}
@@ -0,0 +1,247 @@
+use crate::runtime::lyric::Lyric;
+
+/// A line of the lyric being synchronized, with every time it is sung.
+struct EditorLine {
+    text: String,
+    timestamps: Vec<usize>,
+}
+
+/// State of the synchronization of a plain-text lyric.
+///
+/// Every stamp or nudge records the previous state of the line it changed,
+/// so any edit can be undone.
+pub struct SyncEditor {
+    lines: Vec<EditorLine>,
+    /* ID tags of the lyric loaded, written back when saving */
+    tags: Vec<(String, String)>,
+    current: usize,
+    history: Vec<(usize, Vec<usize>, usize)>,
+}
+
+impl SyncEditor {
+    pub const NUDGE_STEP: isize = 10;
+
+    /// Load the lines of `text`, keeping the timestamps and ID tags already
+    /// present and skipping empty lines.
+    pub fn new(text: &str) -> SyncEditor {
+        let mut lines: Vec<EditorLine> = Vec::new();
+        let mut tags: Vec<(String, String)> = Vec::new();
+
+        for line in text.lines() {
+            let line = line.trim().trim_start_matches('\u{feff}');
+            if line.is_empty() {
+                continue;
+            }
+            if let Some((key, value)) = Lyric::parse_line_tag(line) {
+                tags.push((key.to_string(), value.to_string()));
+                continue;
+            }
+
+            let timestamps = Lyric::parse_line_timestamps(line);
+            let text = if timestamps.is_empty() {
+                line
+            } else {
+                Lyric::parse_line_text(line)
+            };
+
+            lines.push(EditorLine {
+                text: text.to_string(),
+                timestamps,
+            });
+        }
+
+        SyncEditor {
+            lines,
+            tags,
+            current: 0,
+            history: Vec::new(),
+        }
+    }
+
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Whether any line is stamped, loaded with its timestamps or not.
+    pub fn is_stamped(&self) -> bool {
+        self.lines.iter().any(|line| !line.timestamps.is_empty())
+    }
+
+    /// Timestamps of `line` in LRC format.
+    fn format_timestamps(line: &EditorLine) -> String {
+        line.timestamps
+            .iter()
+            .map(|timestamp| format!("[{}]", Lyric::format_timestamp(*timestamp)))
+            .collect()
+    }
+
+    /// Lines as displayed by the editor, with their timestamps if any.
+    pub fn display_lines(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .map(|line| match line.timestamps.is_empty() {
+                true => format!("[--:--.--] {}", line.text),
+                false => format!("{} {}", SyncEditor::format_timestamps(line), line.text),
+            })
+            .collect()
+    }
+
+    fn set_timestamps(&mut self, index: usize, timestamps: Vec<usize>) {
+        let previous = std::mem::replace(&mut self.lines[index].timestamps, timestamps);
+        self.history.push((index, previous, self.current));
+    }
+
+    /// Stamp the current line with `position`, replacing its timestamps, and
+    /// move to the next one.
+    pub fn stamp(&mut self, position: usize) {
+        if self.lines.is_empty() {
+            return;
+        }
+
+        self.set_timestamps(self.current, vec![position]);
+        self.move_down();
+    }
+
+    /// Shift the current line timestamps, or the previous ones when the
+    /// current line is not stamped yet, by `delta` milliseconds.
+    pub fn nudge(&mut self, delta: isize) {
+        let index = match self.lines.get(self.current) {
+            Some(line) if !line.timestamps.is_empty() => self.current,
+            _ if self.current > 0 => self.current - 1,
+            _ => return,
+        };
+
+        if !self.lines[index].timestamps.is_empty() {
+            let timestamps = self.lines[index]
+                .timestamps
+                .iter()
+                .map(|timestamp| timestamp.saturating_add_signed(delta))
+                .collect();
+            self.set_timestamps(index, timestamps);
+        }
+    }
+
+    pub fn undo(&mut self) {
+        if let Some((index, timestamps, current)) = self.history.pop() {
+            self.lines[index].timestamps = timestamps;
+            self.current = current;
+        }
+    }
+
+    pub fn move_up(&mut self) {
+        self.current = self.current.saturating_sub(1);
+    }
+
+    pub fn move_down(&mut self) {
+        if self.current + 1 < self.lines.len() {
+            self.current += 1;
+        }
+    }
+
+    /// The synchronized lyric in LRC format, with the artist and title of
+    /// the song and the other ID tags loaded. Lines not stamped are left out.
+    pub fn to_lrc(&self, artist: &str, title: &str) -> String {
+        let mut lines: Vec<&EditorLine> = self
+            .lines
+            .iter()
+            .filter(|line| !line.timestamps.is_empty())
+            .collect();
+        lines.sort_by_key(|line| line.timestamps.iter().min().copied());
+
+        let mut lrc = format!("[ar:{}]\n[ti:{}]\n", artist, title);
+        for (key, value) in &self.tags {
+            if !key.eq_ignore_ascii_case("ar") && !key.eq_ignore_ascii_case("ti") {
+                lrc.push_str(&format!("[{}:{}]\n", key, value));
+            }
+        }
+        for line in lines {
+            lrc.push_str(&format!(
+                "{}{}\n",
+                SyncEditor::format_timestamps(line),
+                line.text
+            ));
+        }
+
+        lrc
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::editor::SyncEditor;
+
+    #[test]
+    fn load_plain_and_synced_lines() {
+        let editor = SyncEditor::new("[ar:Artist]\nFirst verse\n\n[00:05.00] Second verse\n");
+        assert_eq!(
+            editor.display_lines(),
+            vec!["[--:--.--] First verse", "[00:05.00] Second verse"]
+        );
+    }
+
+    #[test]
+    fn stamp_nudge_and_undo() {
+        let mut editor = SyncEditor::new("one\ntwo\nthree");
+
+        editor.stamp(1000);
+        editor.stamp(2500);
+        assert_eq!(editor.current(), 2);
+
+        /* The current line is not stamped, the previous one is nudged */
+        editor.nudge(SyncEditor::NUDGE_STEP);
+        editor.nudge(-3 * SyncEditor::NUDGE_STEP);
+        assert_eq!(editor.display_lines()[1], "[00:02.48] two");
+
+        editor.undo();
+        editor.undo();
+        assert_eq!(editor.display_lines()[1], "[00:02.50] two");
+
+        editor.undo();
+        assert_eq!(editor.display_lines()[1], "[--:--.--] two");
+        assert_eq!(editor.current(), 1);
+    }
+
+    #[test]
+    fn last_line_stays_selected() {
+        let mut editor = SyncEditor::new("one\ntwo");
+
+        editor.stamp(1000);
+        editor.stamp(2000);
+        editor.stamp(3000);
+        assert_eq!(editor.current(), 1);
+        assert_eq!(editor.display_lines()[1], "[00:03.00] two");
+    }
+
+    #[test]
+    fn lrc_output() {
+        let mut editor = SyncEditor::new("one\ntwo\nthree");
+
+        editor.move_down();
+        editor.stamp(5000);
+        editor.move_up();
+        editor.move_up();
+        editor.stamp(1000);
+
+        assert_eq!(
+            editor.to_lrc("Artist", "Title"),
+            "[ar:Artist]\n[ti:Title]\n[00:01.00]one\n[00:05.00]two\n"
+        );
+    }
+
+    #[test]
+    fn multi_timestamp_round_trip() {
+        let lrc = "[ar:Artist]\n[ti:Title]\n[offset:+200]\n\
+                   [00:05.00]Verse\n[00:10.00][01:20.00]Chorus\n[00:15.00]Bridge\n";
+        let editor = SyncEditor::new(lrc);
+
+        assert!(editor.is_stamped());
+        assert_eq!(editor.display_lines()[1], "[00:10.00][01:20.00] Chorus");
+        assert_eq!(editor.to_lrc("Artist", "Title"), lrc);
+        assert!(!SyncEditor::new("one\ntwo").is_stamped());
+    }
+}
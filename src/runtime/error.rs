@@ -5,15 +5,45 @@ use std::fmt;
 pub enum RuntimeError {
     LyricDirEnvNotSet,
     LyricNotFound,
+    NoSongPlaying,
+    InstrumentalGap,
+    PlayerError(String),
     GuiError(String),
+    NotifyError(String),
     ParseError(String),
     EnvVarError(String),
+    UsageError(String),
+    CheckFailed(usize),
 }
 
 impl fmt::Display for RuntimeError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            _ => write!(f, "error non yet implemented"),
+            Self::LyricDirEnvNotSet => {
+                write!(f, "no lyrics directory set, set LYRICS_DIR or lyrics.dirs")
+            }
+            Self::LyricNotFound => write!(f, "lyric not found"),
+            Self::NoSongPlaying => write!(f, "no song is playing"),
+            Self::InstrumentalGap => write!(f, "no verse is being sung"),
+            Self::PlayerError(error) => write!(f, "can't connect to the player: {}", error),
+            Self::GuiError(error) => write!(f, "{}", error),
+            Self::NotifyError(error) => write!(f, "can't notify: {}", error),
+            Self::ParseError(error) => write!(f, "parse error: {}", error),
+            Self::EnvVarError(error) => write!(f, "environment variable error: {}", error),
+            Self::UsageError(error) => write!(f, "{}", error),
+            Self::CheckFailed(files) => write!(f, "{} lyric files did not pass the check", files),
+        }
+    }
+}
+
+impl RuntimeError {
+    /// Exit status of the `now` command failing with the error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::PlayerError(_) | Self::NoSongPlaying => 2,
+            Self::LyricNotFound | Self::LyricDirEnvNotSet => 3,
+            Self::InstrumentalGap => 4,
+            _ => 1,
         }
     }
 }
@@ -1,10 +1,26 @@
 use crossterm::cursor::{Hide, MoveTo, Show};
-use crossterm::event::{Event, KeyCode};
-use crossterm::style::{Attribute, Print, SetAttribute};
-use crossterm::terminal::{Clear, ClearType, disable_raw_mode, enable_raw_mode, size};
-use crossterm::{Command, queue};
-use md5;
+use crossterm::event::{
+    DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEventKind, KeyModifiers,
+    MouseEventKind,
+};
+use crossterm::style::{Attribute, Print, SetAttribute, SetStyle};
+use crossterm::terminal::{
+    Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode,
+    enable_raw_mode, is_raw_mode_enabled, size,
+};
+use crossterm::{Command, execute, queue};
+use signal_hook::consts::{SIGHUP, SIGINT, SIGTERM};
 use std::io::{Error, Write, stdout};
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+mod text;
+pub mod theme;
+
+use crate::runtime::config;
+use theme::{Style, Theme};
 
 #[derive(Default, Copy, Clone)]
 #[allow(dead_code)]
@@ -19,10 +35,145 @@ pub struct Position {
     pub row: usize,
 }
 
+/// Horizontal alignment of the verses.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Alignment {
+    Left,
+    Center,
+    Right,
+}
+
+impl Alignment {
+    pub fn parse(name: &str) -> Option<Alignment> {
+        match name {
+            "left" => Some(Alignment::Left),
+            "center" => Some(Alignment::Center),
+            "right" => Some(Alignment::Right),
+            _ => None,
+        }
+    }
+
+    /// Column where `row` starts in `width` columns.
+    fn column(&self, row: &str, width: usize) -> usize {
+        match self {
+            Alignment::Left => 0,
+            Alignment::Center => text::centered_column(row, width),
+            Alignment::Right => width.saturating_sub(text::display_width(row)),
+        }
+    }
+}
+
+/// Key of the keyboard, regardless of the modifiers.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Home,
+    End,
+    PageUp,
+    PageDown,
+    Tab,
+    Backspace,
+    Delete,
+    Insert,
+    Enter,
+    Esc,
+    F(u8),
+}
+
+/// Key pressed along with the modifiers held down. Shift is part of the
+/// character for `Key::Char`, so it is only set for the other keys.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct KeyPress {
+    pub key: Key,
+    pub ctrl: bool,
+    pub alt: bool,
+    pub shift: bool,
+}
+
+impl KeyPress {
+    pub const fn plain(key: Key) -> KeyPress {
+        KeyPress {
+            key,
+            ctrl: false,
+            alt: false,
+            shift: false,
+        }
+    }
+
+    pub const fn char(c: char) -> KeyPress {
+        KeyPress::plain(Key::Char(c))
+    }
+
+    pub const fn ctrl(c: char) -> KeyPress {
+        KeyPress {
+            ctrl: true,
+            ..KeyPress::char(c)
+        }
+    }
+
+    /// Character typed, if the key is one without Ctrl or Alt.
+    pub fn text(&self) -> Option<char> {
+        match self.key {
+            Key::Char(c) if !self.ctrl && !self.alt => Some(c),
+            _ => None,
+        }
+    }
+}
+
+/// Input received by the GUI.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum GuiEvent {
+    Key(KeyPress),
+    /// The mouse wheel was scrolled up or down.
+    ScrollUp,
+    ScrollDown,
+    /// A mouse button was pressed on the row.
+    Click(usize),
+    Resize,
+    /// A SIGTERM, SIGHUP or SIGINT was received.
+    Terminate,
+}
+
+/* Set by the signal handlers, checked on every poll */
+static TERMINATE: OnceLock<Arc<AtomicBool>> = OnceLock::new();
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/* Screen row and verse of each row printed by the last `print_vector` */
+static LAYOUT: Mutex<Vec<(usize, usize)>> = Mutex::new(Vec::new());
+
+fn theme() -> &'static Theme {
+    THEME.get_or_init(Theme::default)
+}
+
+/// Scrolling from the verse at `previous` to the active one, `progress`
+/// going from 0 to 1.
+#[derive(Clone, Copy)]
+pub struct Transition {
+    pub previous: usize,
+    pub progress: f32,
+}
+
+/// State of the lyric shown by `Gui::print_vector`.
+#[derive(Default)]
+pub struct Frame<'a> {
+    /// Verse being sung.
+    pub fixed_index: usize,
+    pub transition: Option<Transition>,
+    /// Verse the window is centered on instead of the active one.
+    pub view: Option<usize>,
+    /// Verses matching the search.
+    pub matches: &'a [usize],
+}
+
 pub enum PrintingStyle {
-    FixedTop,
-    FixedCenter,
-    FixedBottom,
+    Top,
+    Center,
+    Bottom,
 }
 
 pub struct Gui;
@@ -30,22 +181,29 @@ struct Terminal;
 
 impl Terminal {
     fn initialize() -> Result<(), Error> {
+        execute!(stdout(), EnterAlternateScreen, EnableMouseCapture)?;
         enable_raw_mode()?;
         Ok(())
     }
 
     fn terminate() -> Result<(), Error> {
+        Self::queue_command(SetAttribute(Attribute::Reset))?;
+        Self::queue_command(DisableMouseCapture)?;
+        Self::queue_command(LeaveAlternateScreen)?;
         Self::execute()?;
         disable_raw_mode()?;
         Ok(())
     }
 
+    /* The cleared cells take the theme background */
     fn clear_screen() -> Result<(), Error> {
+        Self::reset_style()?;
         Self::queue_command(Clear(ClearType::All))?;
         Ok(())
     }
 
     fn clear_line() -> Result<(), Error> {
+        Self::reset_style()?;
         Self::queue_command(Clear(ClearType::CurrentLine))?;
         Ok(())
     }
@@ -70,13 +228,21 @@ impl Terminal {
         Ok(())
     }
 
-    fn set_bold_attribute() -> Result<(), Error> {
-        Self::queue_command(SetAttribute(Attribute::Bold))?;
+    fn set_style(style: &Style) -> Result<(), Error> {
+        Self::queue_command(SetAttribute(Attribute::Reset))?;
+        Self::queue_command(SetStyle(theme().terminal_style(style)))?;
         Ok(())
     }
 
-    fn reset_attributes() -> Result<(), Error> {
-        Self::queue_command(SetAttribute(Attribute::Reset))?;
+    /// Back to the plain style, on the theme background.
+    fn reset_style() -> Result<(), Error> {
+        Self::set_style(&Style::default())
+    }
+
+    fn print_styled(string: &str, style: &Style) -> Result<(), Error> {
+        Self::set_style(style)?;
+        Self::print(string)?;
+        Self::reset_style()?;
         Ok(())
     }
 
@@ -87,19 +253,61 @@ impl Terminal {
         Ok(Size { height, width })
     }
 
-    fn pool_key_press() -> Result<Option<char>, Error> {
-        let key_pressed = match crossterm::event::poll(std::time::Duration::from_millis(100))? {
+    fn pool_event(timeout: Duration) -> Result<Option<GuiEvent>, Error> {
+        if TERMINATE
+            .get()
+            .is_some_and(|terminate| terminate.load(Ordering::Relaxed))
+        {
+            return Ok(Some(GuiEvent::Terminate));
+        }
+
+        let event = match crossterm::event::poll(timeout)? {
             true => match crossterm::event::read()? {
-                Event::Key(event) => match event.code {
-                    KeyCode::Char(c) => Some(c),
+                Event::Key(event) if event.kind != KeyEventKind::Release => {
+                    let key = match event.code {
+                        KeyCode::Char(c) => Some(Key::Char(c)),
+                        KeyCode::Up => Some(Key::Up),
+                        KeyCode::Down => Some(Key::Down),
+                        KeyCode::Left => Some(Key::Left),
+                        KeyCode::Right => Some(Key::Right),
+                        KeyCode::Home => Some(Key::Home),
+                        KeyCode::End => Some(Key::End),
+                        KeyCode::PageUp => Some(Key::PageUp),
+                        KeyCode::PageDown => Some(Key::PageDown),
+                        KeyCode::Tab | KeyCode::BackTab => Some(Key::Tab),
+                        KeyCode::Backspace => Some(Key::Backspace),
+                        KeyCode::Delete => Some(Key::Delete),
+                        KeyCode::Insert => Some(Key::Insert),
+                        KeyCode::Enter => Some(Key::Enter),
+                        KeyCode::Esc => Some(Key::Esc),
+                        KeyCode::F(n) => Some(Key::F(n)),
+                        _ => None,
+                    };
+                    key.map(|key| {
+                        let modifiers = event.modifiers;
+                        GuiEvent::Key(KeyPress {
+                            key,
+                            ctrl: modifiers.contains(KeyModifiers::CONTROL),
+                            alt: modifiers.contains(KeyModifiers::ALT),
+                            shift: !matches!(key, Key::Char(_))
+                                && (modifiers.contains(KeyModifiers::SHIFT)
+                                    || event.code == KeyCode::BackTab),
+                        })
+                    })
+                }
+                Event::Mouse(event) => match event.kind {
+                    MouseEventKind::ScrollUp => Some(GuiEvent::ScrollUp),
+                    MouseEventKind::ScrollDown => Some(GuiEvent::ScrollDown),
+                    MouseEventKind::Down(_) => Some(GuiEvent::Click(event.row as usize)),
                     _ => None,
                 },
+                Event::Resize(_, _) => Some(GuiEvent::Resize),
                 _ => None,
             },
             false => None,
         };
 
-        Ok(key_pressed)
+        Ok(event)
     }
 
     fn execute() -> Result<(), Error> {
@@ -114,10 +322,11 @@ impl Terminal {
 }
 
 impl Gui {
-    const TOP_OFFSET: usize = 2;
-    const BOT_OFFSET: usize = 2;
+    pub const FRAME: Duration = Duration::from_millis(16);
 
-    pub fn initialize() -> Result<(), Error> {
+    pub fn initialize(theme: Theme) -> Result<(), Error> {
+        let _ = THEME.set(theme);
+        Gui::install_handlers()?;
         Terminal::initialize()?;
         Terminal::hide_caret()?;
         Terminal::clear_screen()?;
@@ -125,8 +334,46 @@ impl Gui {
         Ok(())
     }
 
-    pub fn pool_keyboard() -> Result<Option<char>, Error> {
-        Terminal::pool_key_press()
+    /// Wait up to `timeout` for an event.
+    pub fn pool_event(timeout: Duration) -> Result<Option<GuiEvent>, Error> {
+        Terminal::pool_event(timeout)
+    }
+
+    /// Restore the terminal before a panic message is printed, and turn the
+    /// termination signals into `GuiEvent::Terminate`.
+    fn install_handlers() -> Result<(), Error> {
+        let hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            Gui::restore();
+            hook(info);
+        }));
+
+        let terminate = TERMINATE.get_or_init(|| Arc::new(AtomicBool::new(false)));
+        for signal in [SIGTERM, SIGHUP, SIGINT] {
+            signal_hook::flag::register(signal, Arc::clone(terminate))?;
+        }
+
+        Ok(())
+    }
+
+    /// Rows of lyric shown at once.
+    pub fn page_size() -> Result<usize, Error> {
+        Ok(Gui::printable_size(Terminal::size()?))
+    }
+
+    /// Rows available for the lyric between the top and bottom margins.
+    fn printable_size(terminal_size: Size) -> usize {
+        terminal_size
+            .height
+            .saturating_sub(config::get().margin_top + config::get().margin_bottom)
+    }
+
+    /// Restore the terminal if it is still set up, before an error or a
+    /// panic message is printed.
+    pub fn restore() {
+        if is_raw_mode_enabled().unwrap_or(true) {
+            let _ = Gui::terminate();
+        }
     }
 
     pub fn terminate() -> Result<(), Error> {
@@ -138,32 +385,166 @@ impl Gui {
     }
 
     pub fn clear_screen() -> Result<(), Error> {
+        Gui::set_layout(Vec::new());
         Terminal::clear_screen()?;
         Ok(())
     }
 
+    /// Verse printed on the screen `row`, if any.
+    pub fn verse_at(row: usize) -> Option<usize> {
+        let layout = LAYOUT.lock().ok()?;
+        layout
+            .iter()
+            .find(|(screen_row, _)| *screen_row == row)
+            .map(|(_, verse)| *verse)
+    }
+
+    fn set_layout(layout: Vec<(usize, usize)>) {
+        if let Ok(mut current) = LAYOUT.lock() {
+            *current = layout;
+        }
+    }
+
+    /// Screen row and verse of the `range` of rows printed from the screen
+    /// row `first` down to `height`.
+    fn layout(
+        rows: &[(usize, &str)],
+        range: Range<usize>,
+        first: usize,
+        height: usize,
+    ) -> Vec<(usize, usize)> {
+        rows[range]
+            .iter()
+            .enumerate()
+            .map(|(offset, (verse, _))| (first + offset, *verse))
+            .take_while(|(row, _)| *row < height)
+            .collect()
+    }
+
+    /// Print `string` on the first row of the terminal.
+    pub fn print_header(string: &str) -> Result<(), Error> {
+        Gui::print_bar(string, |_| 0, true)
+    }
+
+    /// Print `string` on the last row of the terminal.
+    pub fn print_footer(string: &str) -> Result<(), Error> {
+        Gui::print_bar(string, |height| height - 1, true)
+    }
+
+    /// Print `string` on the row above the footer.
+    pub fn print_status(string: &str) -> Result<(), Error> {
+        Gui::print_bar(string, |height| height - 2, false)
+    }
+
+    /// Print `string` on one of the rows around the lyric, chosen from the
+    /// terminal height. Nothing is printed when there is no room for them.
+    fn print_bar(string: &str, row: fn(usize) -> usize, centered: bool) -> Result<(), Error> {
+        let terminal_size = Terminal::size()?;
+        if Gui::printable_size(terminal_size) == 0 {
+            return Ok(());
+        }
+        let bar = text::truncate(string, terminal_size.width);
+        let col = match centered {
+            true => text::centered_column(bar, terminal_size.width),
+            false => 0,
+        };
+
+        Terminal::move_caret_to(Position {
+            col,
+            row: row(terminal_size.height),
+        })?;
+        Terminal::clear_line()?;
+        Terminal::print_styled(bar, &theme().header)?;
+        Terminal::execute()?;
+        Ok(())
+    }
+
     pub fn print_general_error(string: &str) -> Result<(), Error> {
         Terminal::clear_screen()?;
         Terminal::move_caret_to(Position {
             col: 0,
-            row: Self::TOP_OFFSET,
+            row: config::get().margin_top,
         })?;
-        Terminal::print(string)?;
+        Terminal::print_styled(string, &theme().error)?;
         Terminal::execute()?;
         Ok(())
     }
 
-    pub fn print_lyric_not_found_error(artist: &str, title: &str) -> Result<(), Error> {
-        let digest = md5::compute(format!("{}{}", artist, title).as_bytes());
-
+    pub fn print_lyric_not_found_error(
+        artist: &str,
+        title: &str,
+        digest: &str,
+    ) -> Result<(), Error> {
         let artist_msg: String = format!("Artist: {}", artist);
         let title_msg: String = format!("Title: {}", title);
-        let digest_msg: String = format!("{:x}", digest);
 
-        let debug = vec!["Lyric not found", "", artist_msg.as_str(), title_msg.as_str(), digest_msg.as_str()];
+        let debug = vec![
+            "Lyric not found",
+            "",
+            artist_msg.as_str(),
+            title_msg.as_str(),
+            digest,
+        ];
 
+        let rows = Gui::wrap_verses(&debug, Terminal::size()?.width);
         Terminal::clear_screen()?;
-        Gui::print_vector_slice(&debug, 0, 0, debug.len())?;
+        Gui::print_rows(&rows, 0..rows.len(), |index| match index {
+            0 => theme().error,
+            _ => Style::default(),
+        })?;
+        Terminal::execute()?;
+        Ok(())
+    }
+
+    /// Draw a box listing `keys` and their description centered over the
+    /// screen, leaving the rest of it untouched.
+    pub fn print_help(keys: &[(String, String)]) -> Result<(), Error> {
+        let terminal_size = Terminal::size()?;
+        let key_width = keys
+            .iter()
+            .map(|(key, _)| text::display_width(key))
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec!["Keys".to_string(), String::new()];
+        lines.extend(keys.iter().map(|(key, description)| {
+            let padding = key_width - text::display_width(key);
+            format!("{}{}  {}", key, " ".repeat(padding), description)
+        }));
+
+        /* Two columns of border and two of padding around the text */
+        let longest = lines.iter().map(|line| text::display_width(line)).max();
+        let width = std::cmp::min(longest.unwrap_or(0) + 4, terminal_size.width);
+        let height = std::cmp::min(lines.len() + 2, terminal_size.height);
+        if width < 4 || height < 2 {
+            return Ok(());
+        }
+        let inner = width - 4;
+
+        let mut cursor = Position {
+            col: (terminal_size.width - width) / 2,
+            row: (terminal_size.height - height) / 2,
+        };
+        let border = |left: &str, fill: &str, right: &str| {
+            format!("{}{}{}", left, fill.repeat(width - 2), right)
+        };
+
+        Terminal::move_caret_to(cursor)?;
+        Terminal::print_styled(&border("┌", "─", "┐"), &theme().header)?;
+        for line in lines.iter().take(height - 2) {
+            let line = text::truncate(line, inner);
+            let padding = inner - text::display_width(line);
+
+            cursor.row += 1;
+            Terminal::move_caret_to(cursor)?;
+            Terminal::print_styled("│ ", &theme().header)?;
+            Terminal::print(&format!("{}{}", line, " ".repeat(padding)))?;
+            Terminal::print_styled(" │", &theme().header)?;
+        }
+        cursor.row += 1;
+        Terminal::move_caret_to(cursor)?;
+        Terminal::print_styled(&border("└", "─", "┘"), &theme().header)?;
+
         Terminal::execute()?;
         Ok(())
     }
@@ -172,7 +553,9 @@ impl Gui {
         let terminal_size = Terminal::size()?;
         let mut pos = Position { col: 0, row: 0 };
         for message in debug_messages {
-            pos.col = terminal_size.width - message.len();
+            pos.col = terminal_size
+                .width
+                .saturating_sub(text::display_width(&message));
             Terminal::move_caret_to(pos)?;
             Terminal::print(&message)?;
             pos.row += 1;
@@ -180,38 +563,46 @@ impl Gui {
         Ok(())
     }
 
-    pub fn print_vector(vector: &Vec<&str>, fixed_index: usize) -> Result<(), Error> {
-        let style = Gui::define_printing_style(fixed_index, vector.len())?;
+    /// Print the verses of `vector` around the one at `view`, or around the
+    /// active verse at `fixed_index` when following the song.
+    pub fn print_vector(vector: &[&str], frame: &Frame) -> Result<(), Error> {
+        let fixed_index = frame.fixed_index;
         let terminal_size = Terminal::size()?;
-        let printable_size = terminal_size.height - Self::TOP_OFFSET - Self::BOT_OFFSET;
-        let start;
-        let end;
+        let printable_size = Gui::printable_size(terminal_size);
+        let rows = Gui::wrap_verses(vector, terminal_size.width);
+        let active = Gui::verse_rows(&rows, frame.view.unwrap_or(fixed_index));
+
+        /* No room for the offsets, show only the verse being sung */
+        if printable_size == 0 {
+            return Gui::print_minimal(&rows, &active, terminal_size);
+        }
+
+        let style = Gui::define_printing_style(&active, rows.len(), printable_size);
 
         if cfg!(debug_assertions) {
             Terminal::clear_screen()?;
         }
 
-        match style {
-            PrintingStyle::FixedTop => {
-                start = 0;
-                end = std::cmp::min(printable_size, vector.len());
-                Gui::print_vector_slice(vector, fixed_index, start, end)?;
-            }
-            PrintingStyle::FixedCenter => {
-                start = if (printable_size % 2) == 0 {
-                    fixed_index - printable_size / 2
-                } else {
-                    fixed_index - (printable_size - 1) / 2
-                };
-                end = start + printable_size;
-                Gui::print_vector_slice(vector, fixed_index, start, end)?;
-            }
-            PrintingStyle::FixedBottom => {
-                start = vector.len() - printable_size;
-                end = start + printable_size;
-                Gui::print_vector_slice(vector, fixed_index, start, end)?;
-            }
-        };
+        let mut visible = Gui::visible_rows(&style, &active, rows.len(), printable_size);
+
+        /* Move the window from where it was for the previous verse */
+        if let Some(transition) = frame.transition.filter(|_| frame.view.is_none()) {
+            let previous = Gui::verse_rows(&rows, transition.previous);
+            let previous_style = Gui::define_printing_style(&previous, rows.len(), printable_size);
+            let from = Gui::visible_rows(&previous_style, &previous, rows.len(), printable_size);
+
+            let start = Gui::scroll_start(from.start, visible.start, transition.progress);
+            visible = start..std::cmp::min(start + printable_size, rows.len());
+        }
+
+        let (start, end) = (visible.start, visible.end);
+        Gui::set_layout(Gui::layout(
+            &rows,
+            visible.clone(),
+            config::get().margin_top,
+            terminal_size.height,
+        ));
+        Gui::print_rows(&rows, visible, |index| Gui::verse_style(index, frame))?;
 
         /* Debug print BEGIN */
 
@@ -220,18 +611,17 @@ impl Gui {
                 "DEBUG".to_string(),
                 format!("term height={}", terminal_size.height),
                 format!("term width={}", terminal_size.width),
-                format!(
-                    "printable={}",
-                    terminal_size.height - Self::TOP_OFFSET - Self::BOT_OFFSET
-                ),
+                format!("printable={}", printable_size),
                 format!("fixed index={}", fixed_index),
+                format!("fixed rows={}..{}", active.start, active.end),
                 format!("start={}", start),
                 format!("end={}", end),
                 format!("vector len={}", vector.len()),
+                format!("rows len={}", rows.len()),
                 match style {
-                    PrintingStyle::FixedTop => "style=fixed top".to_string(),
-                    PrintingStyle::FixedCenter => "style=fixed center".to_string(),
-                    PrintingStyle::FixedBottom => "style=fixed bottom".to_string(),
+                    PrintingStyle::Top => "style=fixed top".to_string(),
+                    PrintingStyle::Center => "style=fixed center".to_string(),
+                    PrintingStyle::Bottom => "style=fixed bottom".to_string(),
                 },
             ];
             Gui::print_debug(debug)?;
@@ -243,40 +633,139 @@ impl Gui {
         Ok(())
     }
 
-    fn print_vector_slice(
-        vector: &Vec<&str>,
-        fixed_index: usize,
-        start: usize,
-        end: usize,
+    /// Verses wrapped to `width` columns, each row with the index of its verse.
+    fn wrap_verses<'a>(vector: &[&'a str], width: usize) -> Vec<(usize, &'a str)> {
+        vector
+            .iter()
+            .enumerate()
+            .flat_map(|(index, verse)| {
+                text::wrap(verse, width)
+                    .into_iter()
+                    .map(move |row| (index, row))
+            })
+            .collect()
+    }
+
+    /// Range of the rows taken by the verse at `index`.
+    fn verse_rows(rows: &[(usize, &str)], index: usize) -> Range<usize> {
+        let start = rows
+            .iter()
+            .position(|(verse, _)| *verse == index)
+            .unwrap_or(rows.len());
+        let count = rows[start..]
+            .iter()
+            .take_while(|(verse, _)| *verse == index)
+            .count();
+
+        start..start + count
+    }
+
+    /// Rows of the verses shown on screen.
+    fn visible_rows(
+        style: &PrintingStyle,
+        active: &Range<usize>,
+        rows_size: usize,
+        printable_size: usize,
+    ) -> Range<usize> {
+        let start = match style {
+            PrintingStyle::Top => 0,
+            PrintingStyle::Center => active.start - Gui::top_margin(active, printable_size),
+            PrintingStyle::Bottom => rows_size - printable_size,
+        };
+
+        start..std::cmp::min(start + printable_size, rows_size)
+    }
+
+    /// Print only the rows of the active verse, vertically centered.
+    fn print_minimal(
+        rows: &[(usize, &str)],
+        active: &Range<usize>,
+        terminal_size: Size,
+    ) -> Result<(), Error> {
+        let shown =
+            &rows[active.start..std::cmp::min(active.end, active.start + terminal_size.height)];
+        let mut cursor = Position {
+            col: 0,
+            row: (terminal_size.height - shown.len()) / 2,
+        };
+
+        Terminal::clear_screen()?;
+        Gui::set_layout(Gui::layout(
+            rows,
+            active.start..active.start + shown.len(),
+            cursor.row,
+            terminal_size.height,
+        ));
+        for (_, row) in shown {
+            cursor.col = config::get().alignment.column(row, terminal_size.width);
+            Terminal::move_caret_to(cursor)?;
+            Terminal::print_styled(row, &theme().current)?;
+            cursor.row += 1;
+        }
+        Terminal::execute()?;
+        Ok(())
+    }
+
+    /// Rows shown above the active verse to keep it centered.
+    fn top_margin(active: &Range<usize>, printable_size: usize) -> usize {
+        printable_size.saturating_sub(active.len()).div_ceil(2)
+    }
+
+    /// First row of the window `progress` of the way from `from` to `to`.
+    fn scroll_start(from: usize, to: usize, progress: f32) -> usize {
+        let progress = progress.clamp(0.0, 1.0);
+        let start = from as f32 + (to as f32 - from as f32) * progress;
+        start.round() as usize
+    }
+
+    /// Style of the verse at `index` depending on whether it was sung. During
+    /// a transition the highlight fades from the previous verse to the
+    /// active one.
+    fn verse_style(index: usize, frame: &Frame) -> Style {
+        let fixed_index = frame.fixed_index;
+        let style = |index: usize, fixed_index: usize| match index.cmp(&fixed_index) {
+            std::cmp::Ordering::Less => theme().past,
+            std::cmp::Ordering::Equal => theme().current,
+            std::cmp::Ordering::Greater => theme().upcoming,
+        };
+
+        match frame.transition {
+            _ if index != fixed_index && frame.matches.contains(&index) => theme().matched,
+            Some(Transition { previous, progress })
+                if index != fixed_index && index == previous =>
+            {
+                theme().blend(&theme().current, &style(index, fixed_index), progress)
+            }
+            Some(Transition { previous, progress })
+                if index == fixed_index && index != previous =>
+            {
+                theme().blend(&style(index, previous), &theme().current, progress)
+            }
+            _ => style(index, fixed_index),
+        }
+    }
+
+    /// Print `range` of `rows`, each styled from the index of its verse.
+    fn print_rows(
+        rows: &[(usize, &str)],
+        range: Range<usize>,
+        style: impl Fn(usize) -> Style,
     ) -> Result<(), Error> {
         let term_size = Terminal::size()?;
         let mut cursor = Position {
             col: 0,
-            row: Gui::TOP_OFFSET,
+            row: config::get().margin_top,
         };
 
-        for (index, text) in vector[start..end].iter().enumerate() {
-            let t = if text.len() >= term_size.width {
-                &text[..term_size.width]
-            } else {
-                text
-            };
-
-            cursor.col = if text.len() >= term_size.width {
-                0
-            } else {
-                term_size.width / 2 - text.len() / 2
-            };
+        for (index, row) in &rows[range] {
+            if cursor.row >= term_size.height {
+                break;
+            }
+            cursor.col = config::get().alignment.column(row, term_size.width);
 
             Terminal::move_caret_to(cursor)?;
             Terminal::clear_line()?;
-            if (start + index) == fixed_index {
-                Terminal::set_bold_attribute()?;
-                Terminal::print(t)?;
-                Terminal::reset_attributes()?;
-            } else {
-                Terminal::print(t)?;
-            }
+            Terminal::print_styled(row, &style(*index))?;
             cursor.row += 1;
         }
 
@@ -284,30 +773,104 @@ impl Gui {
     }
 
     fn define_printing_style(
-        fixed_index: usize,
-        vector_size: usize,
-    ) -> Result<PrintingStyle, Error> {
-        let terminal_size = Terminal::size()?;
-        let printable_size = terminal_size.height - Self::TOP_OFFSET - Self::BOT_OFFSET;
+        active: &Range<usize>,
+        rows_size: usize,
+        printable_size: usize,
+    ) -> PrintingStyle {
+        if rows_size <= printable_size {
+            return PrintingStyle::Top;
+        }
 
-        if vector_size < printable_size {
-            return Ok(PrintingStyle::FixedTop);
+        match active
+            .start
+            .checked_sub(Gui::top_margin(active, printable_size))
+        {
+            None | Some(0) => PrintingStyle::Top,
+            Some(start) if start + printable_size >= rows_size => PrintingStyle::Bottom,
+            Some(_) => PrintingStyle::Center,
         }
+    }
+}
 
-        let center_row = if (printable_size % 2) == 0 {
-            printable_size / 2
-        } else {
-            (printable_size + 1) / 2
-        };
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::gui::{Gui, PrintingStyle};
+
+    #[test]
+    fn wrapped_verse_rows() {
+        let vector = vec!["one", "a verse on two rows", "", "three"];
+        let rows = Gui::wrap_verses(&vector, 10);
+
+        assert_eq!(
+            rows,
+            vec![
+                (0, "one"),
+                (1, "a verse on"),
+                (1, "two rows"),
+                (2, ""),
+                (3, "three")
+            ]
+        );
+        assert_eq!(Gui::verse_rows(&rows, 1), 1..3);
+        assert_eq!(Gui::verse_rows(&rows, 3), 4..5);
+        assert_eq!(Gui::verse_rows(&[], 0), 0..0);
+    }
 
-        if fixed_index < center_row {
-            return Ok(PrintingStyle::FixedTop);
-        }
+    #[test]
+    fn rows_layout() {
+        let rows = vec![(0, "one"), (1, "a verse on"), (1, "two rows"), (2, "three")];
 
-        if fixed_index > (vector_size - center_row) {
-            return Ok(PrintingStyle::FixedBottom);
-        }
+        assert_eq!(
+            Gui::layout(&rows, 1..4, 2, 10),
+            vec![(2, 1), (3, 1), (4, 2)]
+        );
+        assert_eq!(Gui::layout(&rows, 0..4, 2, 4), vec![(2, 0), (3, 1)]);
+        assert!(Gui::layout(&rows, 2..2, 0, 10).is_empty());
+    }
+
+    #[test]
+    fn printing_style_on_rows() {
+        /* Everything fits */
+        assert!(matches!(
+            Gui::define_printing_style(&(8..10), 10, 10),
+            PrintingStyle::Top
+        ));
+
+        assert!(matches!(
+            Gui::define_printing_style(&(2..3), 20, 6),
+            PrintingStyle::Top
+        ));
+        assert!(matches!(
+            Gui::define_printing_style(&(10..12), 20, 6),
+            PrintingStyle::Center
+        ));
+        assert!(matches!(
+            Gui::define_printing_style(&(17..18), 20, 6),
+            PrintingStyle::Bottom
+        ));
+
+        /* A single printable row still shows the active verse */
+        let style = Gui::define_printing_style(&(10..11), 20, 1);
+        assert!(matches!(style, PrintingStyle::Center));
+        assert_eq!(Gui::visible_rows(&style, &(10..11), 20, 1), 10..11);
+
+        let style = Gui::define_printing_style(&(19..20), 20, 1);
+        assert!(matches!(style, PrintingStyle::Bottom));
+        assert_eq!(Gui::visible_rows(&style, &(19..20), 20, 1), 19..20);
+
+        /* The active verse starts on top when taller than the screen */
+        assert_eq!(Gui::top_margin(&(10..18), 6), 0);
+        assert_eq!(Gui::top_margin(&(10..12), 6), 2);
+        assert_eq!(Gui::top_margin(&(10..11), 6), 3);
+    }
 
-        Ok(PrintingStyle::FixedCenter)
+    #[test]
+    fn scroll_between_windows() {
+        assert_eq!(Gui::scroll_start(4, 8, 0.0), 4);
+        assert_eq!(Gui::scroll_start(4, 8, 0.5), 6);
+        assert_eq!(Gui::scroll_start(4, 8, 1.0), 8);
+        assert_eq!(Gui::scroll_start(8, 4, 0.3), 7);
+        assert_eq!(Gui::scroll_start(4, 8, 2.0), 8);
     }
 }
@@ -0,0 +1,143 @@
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+/// Number of terminal columns taken by `text`.
+pub fn display_width(text: &str) -> usize {
+    text.width()
+}
+
+/// Longest prefix of `text` fitting in `width` columns, never splitting a
+/// grapheme cluster nor a wide character.
+pub fn truncate(text: &str, width: usize) -> &str {
+    let mut used = 0;
+
+    for (index, grapheme) in text.grapheme_indices(true) {
+        used += grapheme.width();
+        if used > width {
+            return &text[..index];
+        }
+    }
+
+    text
+}
+
+/// Split `text` in rows of at most `width` columns, breaking at whitespace.
+/// Words longer than a row are broken at grapheme boundaries.
+pub fn wrap(text: &str, width: usize) -> Vec<&str> {
+    let mut words: Vec<(usize, &str)> = Vec::new();
+    let mut word_start: Option<usize> = None;
+
+    for (index, c) in text.char_indices() {
+        if !c.is_whitespace() {
+            word_start.get_or_insert(index);
+        } else if let Some(start) = word_start.take() {
+            words.push((start, &text[start..index]));
+        }
+    }
+    if let Some(start) = word_start {
+        words.push((start, &text[start..]));
+    }
+
+    let mut rows: Vec<&str> = Vec::new();
+    let mut row: Option<(usize, usize)> = None;
+
+    for (mut start, mut word) in words {
+        while !word.is_empty() {
+            if let Some((row_start, row_end)) = row {
+                let end = start + word.len();
+                if display_width(&text[row_start..end]) <= width {
+                    row = Some((row_start, end));
+                    break;
+                }
+                rows.push(&text[row_start..row_end]);
+                row = None;
+            }
+
+            if display_width(word) <= width {
+                row = Some((start, start + word.len()));
+                break;
+            }
+
+            /* Always make progress, even if a grapheme is wider than a row */
+            let mut head = truncate(word, width);
+            if head.is_empty() {
+                head = word.graphemes(true).next().unwrap_or(word);
+            }
+            rows.push(head);
+            start += head.len();
+            word = &word[head.len()..];
+        }
+    }
+
+    if let Some((row_start, row_end)) = row {
+        rows.push(&text[row_start..row_end]);
+    }
+    if rows.is_empty() {
+        rows.push("");
+    }
+
+    rows
+}
+
+/// Column where `text` starts when centered in `width` columns.
+pub fn centered_column(text: &str, width: usize) -> usize {
+    (width / 2).saturating_sub(display_width(text) / 2)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::gui::text::{centered_column, display_width, truncate, wrap};
+
+    #[test]
+    fn mixed_script_width() {
+        assert_eq!(display_width("Granite"), 7);
+        assert_eq!(display_width("Ёлка, ёлка"), 10);
+        assert_eq!(display_width("夜に駆ける"), 10);
+        assert_eq!(display_width("café ☕"), 7);
+    }
+
+    #[test]
+    fn truncate_on_grapheme_boundaries() {
+        assert_eq!(truncate("Granite", 4), "Gran");
+        assert_eq!(truncate("Granite", 20), "Granite");
+
+        /* A wide glyph is dropped rather than cut in half */
+        assert_eq!(truncate("夜に駆ける", 5), "夜に");
+        assert_eq!(truncate("夜に駆ける", 6), "夜に駆");
+
+        /* Combining accents stay with their base letter */
+        assert_eq!(truncate("cafe\u{301} noir", 4), "cafe\u{301}");
+        assert_eq!(truncate("Ёлка", 2), "Ёл");
+        assert_eq!(truncate("👍🏽 ok", 1), "");
+    }
+
+    #[test]
+    fn wrap_at_words() {
+        assert_eq!(wrap("", 10), vec![""]);
+        assert_eq!(wrap("   ", 10), vec![""]);
+        assert_eq!(wrap("short verse", 20), vec!["short verse"]);
+        assert_eq!(
+            wrap("I can't stand  the way you stare", 12),
+            vec!["I can't", "stand  the", "way you", "stare"]
+        );
+        assert_eq!(
+            wrap("Ёлка ёлка лесной аромат", 10),
+            vec!["Ёлка ёлка", "лесной", "аромат"]
+        );
+    }
+
+    #[test]
+    fn wrap_long_words() {
+        assert_eq!(wrap("abcdefghij klm", 4), vec!["abcd", "efgh", "ij", "klm"]);
+        assert_eq!(wrap("夜に駆ける 夜", 4), vec!["夜に", "駆け", "る", "夜"]);
+        assert_eq!(wrap("夜に", 1), vec!["夜", "に"]);
+    }
+
+    #[test]
+    fn center_wide_glyphs() {
+        assert_eq!(centered_column("abcd", 20), 8);
+        assert_eq!(centered_column("夜に駆ける", 20), 5);
+        assert_eq!(centered_column("夜に駆ける", 6), 0);
+    }
+}
@@ -0,0 +1,570 @@
+use crossterm::style::{self, Attribute, Attributes, ContentStyle};
+use std::env;
+
+use crate::runtime::config;
+use crate::runtime::error::RuntimeError;
+
+/// Colors a terminal can display.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ColorSupport {
+    None,
+    Basic,
+    Indexed,
+    TrueColor,
+}
+
+impl ColorSupport {
+    /// Guess the color support from the `NO_COLOR`, `COLORTERM` and `TERM`
+    /// environment variables values.
+    pub fn detect(no_color: Option<&str>, colorterm: Option<&str>, term: Option<&str>) -> Self {
+        if no_color.is_some_and(|value| !value.is_empty()) {
+            return ColorSupport::None;
+        }
+
+        match (colorterm, term) {
+            (Some("truecolor" | "24bit"), _) => ColorSupport::TrueColor,
+            (_, Some("dumb")) => ColorSupport::None,
+            (_, Some(term)) if term.contains("256color") => ColorSupport::Indexed,
+            _ => ColorSupport::Basic,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Color {
+    /// One of the 16 standard colors.
+    Ansi(u8),
+    /// One of the 256 colors of the xterm palette.
+    Indexed(u8),
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    const ANSI_NAMES: [&str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+
+    /* xterm default values of the 16 standard colors */
+    const ANSI_RGB: [(u8, u8, u8); 16] = [
+        (0, 0, 0),
+        (205, 0, 0),
+        (0, 205, 0),
+        (205, 205, 0),
+        (0, 0, 238),
+        (205, 0, 205),
+        (0, 205, 205),
+        (229, 229, 229),
+        (127, 127, 127),
+        (255, 0, 0),
+        (0, 255, 0),
+        (255, 255, 0),
+        (92, 92, 255),
+        (255, 0, 255),
+        (0, 255, 255),
+        (255, 255, 255),
+    ];
+
+    const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+    /* Terminal colors of the 16 standard colors, in the same order */
+    const TERMINAL_ANSI: [style::Color; 16] = [
+        style::Color::Black,
+        style::Color::DarkRed,
+        style::Color::DarkGreen,
+        style::Color::DarkYellow,
+        style::Color::DarkBlue,
+        style::Color::DarkMagenta,
+        style::Color::DarkCyan,
+        style::Color::Grey,
+        style::Color::DarkGrey,
+        style::Color::Red,
+        style::Color::Green,
+        style::Color::Yellow,
+        style::Color::Blue,
+        style::Color::Magenta,
+        style::Color::Cyan,
+        style::Color::White,
+    ];
+
+    /// Parse a color name ("red", "bright-blue"), an xterm palette index or
+    /// an hex value ("#rrggbb").
+    pub fn parse(value: &str) -> Option<Color> {
+        if let Some(hex) = value.strip_prefix('#') {
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return None;
+            }
+            let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+            return Some(Color::Rgb(channel(0)?, channel(2)?, channel(4)?));
+        }
+
+        if let Ok(index) = value.parse::<u8>() {
+            return Some(Color::Indexed(index));
+        }
+
+        let (name, bright) = match value.strip_prefix("bright-") {
+            Some(name) => (name, 8),
+            None => (value, 0),
+        };
+        Color::ANSI_NAMES
+            .iter()
+            .position(|ansi| *ansi == name)
+            .map(|index| Color::Ansi(index as u8 + bright))
+    }
+
+    fn rgb(&self) -> (u8, u8, u8) {
+        match *self {
+            Color::Ansi(index) => Color::ANSI_RGB[index as usize % 16],
+            Color::Indexed(index @ 0..16) => Color::ANSI_RGB[index as usize],
+            Color::Indexed(index @ 16..232) => {
+                let index = (index - 16) as usize;
+                (
+                    Color::CUBE_LEVELS[index / 36],
+                    Color::CUBE_LEVELS[index / 6 % 6],
+                    Color::CUBE_LEVELS[index % 6],
+                )
+            }
+            Color::Indexed(index) => {
+                let gray = 8 + (index - 232) * 10;
+                (gray, gray, gray)
+            }
+            Color::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+
+    fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+        let d = |x: u8, y: u8| (x as i32 - y as i32).unsigned_abs().pow(2);
+        d(a.0, b.0) + d(a.1, b.1) + d(a.2, b.2)
+    }
+
+    /// Closest of the 16 standard colors.
+    fn to_ansi(self) -> u8 {
+        let rgb = self.rgb();
+        (0..16)
+            .min_by_key(|index| Color::distance(rgb, Color::ANSI_RGB[*index as usize]))
+            .unwrap_or(0)
+    }
+
+    /// Closest color of the xterm 6x6x6 cube or grayscale ramp.
+    fn to_indexed(self) -> u8 {
+        let (r, g, b) = self.rgb();
+        let level = |value: u8| {
+            (0..6)
+                .min_by_key(|i| (Color::CUBE_LEVELS[*i] as i32 - value as i32).abs())
+                .unwrap_or(0) as u8
+        };
+
+        let cube = 16 + 36 * level(r) + 6 * level(g) + level(b);
+        let average = (r as u32 + g as u32 + b as u32) / 3;
+        let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+
+        match Color::distance((r, g, b), Color::Indexed(gray).rgb())
+            < Color::distance((r, g, b), Color::Indexed(cube).rgb())
+        {
+            true => gray,
+            false => cube,
+        }
+    }
+
+    /// Terminal color drawing the color, downgraded to what the terminal
+    /// supports. `None` when colors are disabled.
+    fn to_terminal(self, support: ColorSupport) -> Option<style::Color> {
+        match (support, self) {
+            (ColorSupport::None, _) => None,
+            (_, Color::Ansi(index)) => Some(Color::TERMINAL_ANSI[index as usize % 16]),
+            (ColorSupport::Basic, color) => Some(Color::TERMINAL_ANSI[color.to_ansi() as usize]),
+            (ColorSupport::Indexed, Color::Rgb(..)) => {
+                Some(style::Color::AnsiValue(self.to_indexed()))
+            }
+            (_, Color::Indexed(index)) => Some(style::Color::AnsiValue(index)),
+            (ColorSupport::TrueColor, Color::Rgb(r, g, b)) => Some(style::Color::Rgb { r, g, b }),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Default, Clone, Copy)]
+pub struct Style {
+    pub foreground: Option<Color>,
+    pub background: Option<Color>,
+    pub bold: bool,
+    pub dim: bool,
+    pub italic: bool,
+    pub underline: bool,
+}
+
+impl Style {
+    const PLAIN: Style = Style {
+        foreground: None,
+        background: None,
+        bold: false,
+        dim: false,
+        italic: false,
+        underline: false,
+    };
+
+    const BOLD: Style = Style {
+        bold: true,
+        ..Style::PLAIN
+    };
+
+    const DIM: Style = Style {
+        dim: true,
+        ..Style::PLAIN
+    };
+
+    const UNDERLINE: Style = Style {
+        underline: true,
+        ..Style::PLAIN
+    };
+
+    const fn fg(color: Color) -> Style {
+        Style {
+            foreground: Some(color),
+            ..Style::PLAIN
+        }
+    }
+
+    /// Parse words separated by spaces or '+': attributes ("bold", "dim",
+    /// "italic", "underline"), a foreground color and "on-" a background
+    /// color, e.g. "bold yellow on-#1c2633".
+    fn parse(value: &str) -> Result<Style, String> {
+        let mut style = Style::default();
+
+        for word in value.split([' ', '+']).filter(|word| !word.is_empty()) {
+            match word {
+                "bold" => style.bold = true,
+                "dim" => style.dim = true,
+                "italic" => style.italic = true,
+                "underline" => style.underline = true,
+                "none" => {}
+                _ => {
+                    let (color, target) = match word.strip_prefix("on-") {
+                        Some(color) => (color, &mut style.background),
+                        None => (word, &mut style.foreground),
+                    };
+                    *target = Some(
+                        Color::parse(color)
+                            .ok_or(format!("unknown color or attribute '{}'", word))?,
+                    );
+                }
+            }
+        }
+
+        Ok(style)
+    }
+}
+
+/// Styles of the parts of the GUI.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Theme {
+    pub past: Style,
+    pub current: Style,
+    pub upcoming: Style,
+    /// Verses matching the search.
+    pub matched: Style,
+    pub header: Style,
+    pub error: Style,
+    pub background: Option<Color>,
+    pub support: ColorSupport,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            past: Style::default(),
+            current: Style::BOLD,
+            upcoming: Style::default(),
+            matched: Style::UNDERLINE,
+            header: Style::DIM,
+            error: Style::BOLD,
+            background: None,
+            support: ColorSupport::Basic,
+        }
+    }
+}
+
+impl Theme {
+    pub const NAMES: [&str; 3] = ["default", "dim", "ocean"];
+
+    pub fn builtin(name: &str) -> Option<Theme> {
+        let theme = match name {
+            "default" => Theme::default(),
+            /* Sung verses fade away, the current one stands out */
+            "dim" => Theme {
+                past: Style::DIM,
+                current: Style {
+                    foreground: Some(Color::Ansi(15)),
+                    ..Style::BOLD
+                },
+                upcoming: Style::default(),
+                ..Theme::default()
+            },
+            "ocean" => Theme {
+                past: Style::fg(Color::Rgb(0x4e, 0x6e, 0x81)),
+                current: Style {
+                    foreground: Some(Color::Rgb(0x8f, 0xd3, 0xfe)),
+                    ..Style::BOLD
+                },
+                upcoming: Style::fg(Color::Rgb(0xb8, 0xc7, 0xd6)),
+                matched: Style {
+                    foreground: Some(Color::Rgb(0xf0, 0xc6, 0x74)),
+                    ..Style::UNDERLINE
+                },
+                header: Style::fg(Color::Rgb(0x6b, 0x8b, 0xa4)),
+                error: Style {
+                    foreground: Some(Color::Rgb(0xff, 0x87, 0x87)),
+                    ..Style::BOLD
+                },
+                background: Some(Color::Rgb(0x1b, 0x26, 0x33)),
+                ..Theme::default()
+            },
+            _ => return None,
+        };
+
+        Some(theme)
+    }
+
+    /// Parse a theme name optionally followed by ';' separated overrides of
+    /// its parts, e.g. "dim;current=bold yellow;background=#1c2633".
+    pub fn parse(spec: &str, support: ColorSupport) -> Result<Theme, String> {
+        let mut parts = spec.split(';').map(str::trim);
+        let name = match parts.next() {
+            Some("") | None => "default",
+            Some(name) => name,
+        };
+
+        let mut theme = Theme::builtin(name).ok_or(format!(
+            "unknown theme '{}', available themes: {}",
+            name,
+            Theme::NAMES.join(", ")
+        ))?;
+        theme.support = support;
+
+        for part in parts.filter(|part| !part.is_empty()) {
+            let (key, value) = part
+                .split_once('=')
+                .ok_or(format!("expected PART=STYLE, found '{}'", part))?;
+            let value = value.trim();
+
+            let target = match key.trim() {
+                "past" => &mut theme.past,
+                "current" => &mut theme.current,
+                "upcoming" => &mut theme.upcoming,
+                "match" => &mut theme.matched,
+                "header" => &mut theme.header,
+                "error" => &mut theme.error,
+                "background" => {
+                    theme.background = match value {
+                        "none" => None,
+                        _ => Some(Color::parse(value).ok_or(format!("unknown color '{}'", value))?),
+                    };
+                    continue;
+                }
+                key => return Err(format!("unknown theme part '{}'", key)),
+            };
+            *target = Style::parse(value)?;
+        }
+
+        Ok(theme)
+    }
+
+    /// Theme of the configuration, for the colors supported by the terminal.
+    pub fn from_config() -> Result<Theme, RuntimeError> {
+        let var = |name: &str| env::var(name).ok();
+        let support = ColorSupport::detect(
+            var("NO_COLOR").as_deref(),
+            var("COLORTERM").as_deref(),
+            var("TERM").as_deref(),
+        );
+
+        Theme::parse(&config::get().theme, support)
+            .map_err(|error| RuntimeError::ParseError(format!("theme: {}", error)))
+    }
+
+    /// Terminal style drawing `style`, on the theme background unless the
+    /// style has its own.
+    pub fn terminal_style(&self, style: &Style) -> ContentStyle {
+        let mut attributes = Attributes::default();
+        for (enabled, attribute) in [
+            (style.bold, Attribute::Bold),
+            (style.dim, Attribute::Dim),
+            (style.italic, Attribute::Italic),
+            (style.underline, Attribute::Underlined),
+        ] {
+            if enabled {
+                attributes.set(attribute);
+            }
+        }
+
+        ContentStyle {
+            foreground_color: style.foreground.and_then(|c| c.to_terminal(self.support)),
+            background_color: style
+                .background
+                .or(self.background)
+                .and_then(|c| c.to_terminal(self.support)),
+            underline_color: None,
+            attributes,
+        }
+    }
+
+    /// Style `progress` of the way from `from` to `to`. Foreground colors are
+    /// faded on truecolor terminals only, other terminals switch at once.
+    pub fn blend(&self, from: &Style, to: &Style, progress: f32) -> Style {
+        let (Some(start), Some(end), ColorSupport::TrueColor) =
+            (from.foreground, to.foreground, self.support)
+        else {
+            return *to;
+        };
+
+        let progress = progress.clamp(0.0, 1.0);
+        let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * progress).round() as u8;
+        let ((r1, g1, b1), (r2, g2, b2)) = (start.rgb(), end.rgb());
+
+        Style {
+            foreground: Some(Color::Rgb(
+                channel(r1, r2),
+                channel(g1, g2),
+                channel(b1, b2),
+            )),
+            ..match progress < 0.5 {
+                true => *from,
+                false => *to,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crossterm::style::{self, Attribute};
+
+    use crate::runtime::gui::theme::{Color, ColorSupport, Style, Theme};
+
+    #[test]
+    fn color_support_detection() {
+        let detect = ColorSupport::detect;
+        assert_eq!(
+            detect(Some("1"), Some("truecolor"), None),
+            ColorSupport::None
+        );
+        assert_eq!(
+            detect(Some(""), Some("truecolor"), None),
+            ColorSupport::TrueColor
+        );
+        assert_eq!(
+            detect(None, None, Some("xterm-256color")),
+            ColorSupport::Indexed
+        );
+        assert_eq!(detect(None, None, Some("dumb")), ColorSupport::None);
+        assert_eq!(detect(None, None, Some("linux")), ColorSupport::Basic);
+    }
+
+    #[test]
+    fn parse_colors() {
+        assert_eq!(Color::parse("red"), Some(Color::Ansi(1)));
+        assert_eq!(Color::parse("bright-white"), Some(Color::Ansi(15)));
+        assert_eq!(Color::parse("208"), Some(Color::Indexed(208)));
+        assert_eq!(Color::parse("#1c2633"), Some(Color::Rgb(0x1c, 0x26, 0x33)));
+        assert_eq!(Color::parse("#1c263"), None);
+        assert_eq!(Color::parse("purple"), None);
+    }
+
+    #[test]
+    fn downgrade_colors() {
+        let orange = Color::Rgb(0xff, 0x87, 0x00);
+        assert_eq!(orange.to_indexed(), 208);
+        assert_eq!(Color::Rgb(0x80, 0x80, 0x80).to_indexed(), 244);
+        assert_eq!(orange.to_ansi(), 3);
+        assert_eq!(Color::Indexed(196).to_ansi(), 9);
+
+        assert_eq!(
+            orange.to_terminal(ColorSupport::TrueColor),
+            Some(style::Color::Rgb {
+                r: 255,
+                g: 135,
+                b: 0
+            })
+        );
+        assert_eq!(
+            orange.to_terminal(ColorSupport::Indexed),
+            Some(style::Color::AnsiValue(208))
+        );
+        assert_eq!(
+            orange.to_terminal(ColorSupport::Basic),
+            Some(style::Color::DarkYellow)
+        );
+        assert_eq!(
+            Color::Ansi(12).to_terminal(ColorSupport::TrueColor),
+            Some(style::Color::Blue)
+        );
+        assert_eq!(orange.to_terminal(ColorSupport::None), None);
+    }
+
+    #[test]
+    fn parse_theme_overrides() {
+        let theme = Theme::parse(
+            "dim; current=bold+yellow on-blue ;background=#000000",
+            ColorSupport::TrueColor,
+        )
+        .unwrap();
+
+        assert_eq!(theme.past, Theme::builtin("dim").unwrap().past);
+        assert_eq!(
+            theme.current,
+            Style {
+                foreground: Some(Color::Ansi(3)),
+                background: Some(Color::Ansi(4)),
+                bold: true,
+                ..Style::default()
+            }
+        );
+        assert_eq!(theme.background, Some(Color::Rgb(0, 0, 0)));
+
+        assert_eq!(
+            Theme::parse("", ColorSupport::Basic).unwrap(),
+            Theme::default()
+        );
+        assert!(Theme::parse("neon", ColorSupport::Basic).is_err());
+        assert!(Theme::parse("default;current=blink", ColorSupport::Basic).is_err());
+        assert!(Theme::parse("default;footer=bold", ColorSupport::Basic).is_err());
+    }
+
+    #[test]
+    fn blend_styles() {
+        let from = Style::fg(Color::Rgb(0, 100, 200));
+        let to = Style {
+            foreground: Some(Color::Rgb(200, 100, 0)),
+            ..Style::BOLD
+        };
+
+        let theme = Theme::parse("default", ColorSupport::TrueColor).unwrap();
+        let middle = theme.blend(&from, &to, 0.5);
+        assert_eq!(middle.foreground, Some(Color::Rgb(100, 100, 100)));
+        assert!(middle.bold);
+        assert!(!theme.blend(&from, &to, 0.25).bold);
+
+        let theme = Theme::parse("default", ColorSupport::Indexed).unwrap();
+        assert_eq!(theme.blend(&from, &to, 0.25), to);
+    }
+
+    #[test]
+    fn terminal_styles() {
+        let theme = Theme::parse("ocean", ColorSupport::Indexed).unwrap();
+        let plain = theme.terminal_style(&Style::default());
+        assert_eq!(plain.foreground_color, None);
+        assert_eq!(plain.background_color, Some(style::Color::AnsiValue(235)));
+        assert!(plain.attributes.is_empty());
+
+        let current = theme.terminal_style(&theme.current);
+        assert_eq!(current.foreground_color, Some(style::Color::AnsiValue(117)));
+        assert_eq!(current.background_color, Some(style::Color::AnsiValue(235)));
+        assert!(current.attributes.has(Attribute::Bold));
+
+        /* NO_COLOR keeps the attributes */
+        let theme = Theme::parse("ocean", ColorSupport::None).unwrap();
+        let current = theme.terminal_style(&theme.current);
+        assert_eq!(current.foreground_color, None);
+        assert_eq!(current.background_color, None);
+        assert!(current.attributes.has(Attribute::Bold));
+    }
+}
@@ -0,0 +1,435 @@
+use crate::runtime::config;
+use crate::runtime::error::RuntimeError;
+use crate::runtime::gui::{GuiEvent, Key, KeyPress};
+
+/// What the user can ask the TUI to do.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Action {
+    Quit,
+    Retry,
+    SongEarlier,
+    SongLater,
+    ResetSongOffset,
+    GlobalEarlier,
+    GlobalLater,
+    SaveOffset,
+    NextVerse,
+    PreviousVerse,
+    NextPage,
+    PreviousPage,
+    FirstVerse,
+    LastVerse,
+    Follow,
+    Search,
+    NextMatch,
+    PreviousMatch,
+    Seek,
+    Help,
+}
+
+impl Action {
+    /// Every action, in the order listed by the help.
+    pub const ALL: [Action; 20] = [
+        Action::Help,
+        Action::Quit,
+        Action::Retry,
+        Action::SongEarlier,
+        Action::SongLater,
+        Action::ResetSongOffset,
+        Action::GlobalEarlier,
+        Action::GlobalLater,
+        Action::SaveOffset,
+        Action::NextVerse,
+        Action::PreviousVerse,
+        Action::NextPage,
+        Action::PreviousPage,
+        Action::FirstVerse,
+        Action::LastVerse,
+        Action::Follow,
+        Action::Search,
+        Action::NextMatch,
+        Action::PreviousMatch,
+        Action::Seek,
+    ];
+
+    /// Name of the action in the keybindings.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Quit => "quit",
+            Action::Retry => "retry",
+            Action::SongEarlier => "song-earlier",
+            Action::SongLater => "song-later",
+            Action::ResetSongOffset => "reset-song-offset",
+            Action::GlobalEarlier => "global-earlier",
+            Action::GlobalLater => "global-later",
+            Action::SaveOffset => "save-offset",
+            Action::NextVerse => "next-verse",
+            Action::PreviousVerse => "previous-verse",
+            Action::NextPage => "next-page",
+            Action::PreviousPage => "previous-page",
+            Action::FirstVerse => "first-verse",
+            Action::LastVerse => "last-verse",
+            Action::Follow => "follow",
+            Action::Search => "search",
+            Action::NextMatch => "next-match",
+            Action::PreviousMatch => "previous-match",
+            Action::Seek => "seek",
+            Action::Help => "help",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+
+    /// Description of the action in the help, with the offset step of the
+    /// configuration.
+    pub fn description(&self) -> String {
+        let step = config::get().offset_step;
+        match self {
+            Action::Quit => "Quit".to_string(),
+            Action::Retry => "Look for the lyric again".to_string(),
+            Action::SongEarlier => format!("Show the song lyric {} ms earlier", step),
+            Action::SongLater => format!("Show the song lyric {} ms later", step),
+            Action::ResetSongOffset => "Reset the song offset".to_string(),
+            Action::GlobalEarlier => format!("Show every lyric {} ms earlier", step),
+            Action::GlobalLater => format!("Show every lyric {} ms later", step),
+            Action::SaveOffset => "Save the song offset in its lyric file".to_string(),
+            Action::NextVerse => "Browse to the next verse".to_string(),
+            Action::PreviousVerse => "Browse to the previous verse".to_string(),
+            Action::NextPage => "Browse a screen down".to_string(),
+            Action::PreviousPage => "Browse a screen up".to_string(),
+            Action::FirstVerse => "Browse to the first verse".to_string(),
+            Action::LastVerse => "Browse to the last verse".to_string(),
+            Action::Follow => "Follow the song, clear the search".to_string(),
+            Action::Search => "Search a text in the lyric".to_string(),
+            Action::NextMatch => "Browse to the next match".to_string(),
+            Action::PreviousMatch => "Browse to the previous match".to_string(),
+            Action::Seek => "Seek cmus to the verse in view".to_string(),
+            Action::Help => "Show or hide this help".to_string(),
+        }
+    }
+}
+
+/* Keys bound to each action when the keybindings don't change them */
+const DEFAULT: [(GuiEvent, Action); 29] = [
+    (GuiEvent::Key(KeyPress::char('q')), Action::Quit),
+    (GuiEvent::Key(KeyPress::ctrl('c')), Action::Quit),
+    (GuiEvent::Key(KeyPress::char('r')), Action::Retry),
+    (GuiEvent::Key(KeyPress::char('+')), Action::SongEarlier),
+    (GuiEvent::Key(KeyPress::char('=')), Action::SongEarlier),
+    (GuiEvent::Key(KeyPress::char('-')), Action::SongLater),
+    (GuiEvent::Key(KeyPress::char('0')), Action::ResetSongOffset),
+    (GuiEvent::Key(KeyPress::char('>')), Action::GlobalEarlier),
+    (GuiEvent::Key(KeyPress::char('<')), Action::GlobalLater),
+    (GuiEvent::Key(KeyPress::char('s')), Action::SaveOffset),
+    (GuiEvent::Key(KeyPress::char('j')), Action::NextVerse),
+    (GuiEvent::Key(KeyPress::plain(Key::Down)), Action::NextVerse),
+    (GuiEvent::ScrollDown, Action::NextVerse),
+    (GuiEvent::Key(KeyPress::char('k')), Action::PreviousVerse),
+    (
+        GuiEvent::Key(KeyPress::plain(Key::Up)),
+        Action::PreviousVerse,
+    ),
+    (GuiEvent::ScrollUp, Action::PreviousVerse),
+    (
+        GuiEvent::Key(KeyPress::plain(Key::PageDown)),
+        Action::NextPage,
+    ),
+    (
+        GuiEvent::Key(KeyPress::plain(Key::PageUp)),
+        Action::PreviousPage,
+    ),
+    (GuiEvent::Key(KeyPress::char('g')), Action::FirstVerse),
+    (
+        GuiEvent::Key(KeyPress::plain(Key::Home)),
+        Action::FirstVerse,
+    ),
+    (GuiEvent::Key(KeyPress::char('G')), Action::LastVerse),
+    (GuiEvent::Key(KeyPress::plain(Key::End)), Action::LastVerse),
+    (GuiEvent::Key(KeyPress::plain(Key::Esc)), Action::Follow),
+    (GuiEvent::Key(KeyPress::char('/')), Action::Search),
+    (GuiEvent::Key(KeyPress::char('n')), Action::NextMatch),
+    (GuiEvent::Key(KeyPress::char('N')), Action::PreviousMatch),
+    (GuiEvent::Key(KeyPress::plain(Key::Enter)), Action::Seek),
+    (GuiEvent::Key(KeyPress::char('?')), Action::Help),
+    (GuiEvent::Key(KeyPress::plain(Key::F(1))), Action::Help),
+];
+
+/* Names of the keys that aren't a single character */
+const KEY_NAMES: [(&str, Key); 17] = [
+    ("space", Key::Char(' ')),
+    ("semicolon", Key::Char(';')),
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("tab", Key::Tab),
+    ("backspace", Key::Backspace),
+    ("delete", Key::Delete),
+    ("insert", Key::Insert),
+    ("enter", Key::Enter),
+    ("esc", Key::Esc),
+    ("escape", Key::Esc),
+];
+
+/// Keys bound to each action of the TUI.
+pub struct Keymap {
+    bindings: Vec<(GuiEvent, Action)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Keymap {
+        Keymap {
+            bindings: DEFAULT.to_vec(),
+        }
+    }
+}
+
+impl Keymap {
+    /// Default keymap changed by `spec`, a list of "action=KEY KEY..."
+    /// separated by ';'. The listed keys replace those bound to the action
+    /// by default, and are taken from any other action they were bound to.
+    pub fn parse(spec: &str) -> Result<Keymap, String> {
+        let mut keymap = Keymap::default();
+
+        for binding in spec.split(';').map(str::trim).filter(|b| !b.is_empty()) {
+            let (name, keys) = binding
+                .split_once('=')
+                .ok_or(format!("expected ACTION=KEYS, found '{}'", binding))?;
+            let action =
+                Action::parse(name.trim()).ok_or(format!("unknown action '{}'", name.trim()))?;
+            let events = keys
+                .split_whitespace()
+                .map(|key| parse_event(key).ok_or(format!("unknown key '{}'", key)))
+                .collect::<Result<Vec<GuiEvent>, String>>()?;
+
+            keymap
+                .bindings
+                .retain(|(event, bound)| *bound != action && !events.contains(event));
+            keymap
+                .bindings
+                .extend(events.into_iter().map(|event| (event, action)));
+        }
+
+        Ok(keymap)
+    }
+
+    /// Keymap changed by the keybindings of the configuration.
+    pub fn from_config() -> Result<Keymap, RuntimeError> {
+        Keymap::parse(&config::get().keybindings)
+            .map_err(|error| RuntimeError::ParseError(format!("keybindings: {}", error)))
+    }
+
+    /// Action bound to `event`, if any.
+    pub fn action(&self, event: GuiEvent) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(key, _)| *key == event)
+            .map(|(_, action)| *action)
+    }
+
+    /// Keys and description of every bound action, as listed by the help.
+    pub fn help(&self) -> Vec<(String, String)> {
+        Action::ALL
+            .iter()
+            .filter_map(|action| {
+                let keys: Vec<String> = self
+                    .bindings
+                    .iter()
+                    .filter(|(_, bound)| bound == action)
+                    .map(|(event, _)| event_name(*event))
+                    .collect();
+
+                (!keys.is_empty()).then(|| (keys.join(", "), action.description()))
+            })
+            .collect()
+    }
+}
+
+/// Event of a key name such as "q", "ctrl-c", "alt-shift-left", "f5" or
+/// "wheel-up".
+fn parse_event(name: &str) -> Option<GuiEvent> {
+    match name {
+        "wheel-up" => return Some(GuiEvent::ScrollUp),
+        "wheel-down" => return Some(GuiEvent::ScrollDown),
+        _ => {}
+    }
+
+    let mut press = KeyPress::plain(Key::Esc);
+    let mut rest = name;
+    /* A single character is a key even when it is '-' */
+    while rest.chars().nth(1).is_some() {
+        let Some((modifier, key)) = rest.split_once('-') else {
+            break;
+        };
+        match modifier.to_lowercase().as_str() {
+            "ctrl" | "c" => press.ctrl = true,
+            "alt" | "a" | "m" => press.alt = true,
+            "shift" | "s" => press.shift = true,
+            _ => return None,
+        }
+        rest = key;
+    }
+
+    let mut chars = rest.chars();
+    press.key = match (chars.next(), chars.next()) {
+        (Some(c), None) => Key::Char(c),
+        _ => {
+            let lowercase = rest.to_lowercase();
+            match KEY_NAMES.iter().find(|(name, _)| *name == lowercase) {
+                Some((_, key)) => *key,
+                None => Key::F(lowercase.strip_prefix('f')?.parse().ok()?),
+            }
+        }
+    };
+
+    /* Shift is part of the character, as in the events */
+    if let Key::Char(c) = press.key
+        && press.shift
+    {
+        press.shift = false;
+        press.key = Key::Char(c.to_uppercase().next().unwrap_or(c));
+    }
+
+    Some(GuiEvent::Key(press))
+}
+
+fn event_name(event: GuiEvent) -> String {
+    let press = match event {
+        GuiEvent::Key(press) => press,
+        GuiEvent::ScrollUp => return "Wheel up".to_string(),
+        GuiEvent::ScrollDown => return "Wheel down".to_string(),
+        GuiEvent::Click(_) | GuiEvent::Resize | GuiEvent::Terminate => return String::new(),
+    };
+
+    let key = match press.key {
+        Key::Char(' ') => "Space".to_string(),
+        Key::Char(c) => c.to_string(),
+        Key::Up => "Up".to_string(),
+        Key::Down => "Down".to_string(),
+        Key::Left => "Left".to_string(),
+        Key::Right => "Right".to_string(),
+        Key::Home => "Home".to_string(),
+        Key::End => "End".to_string(),
+        Key::PageUp => "PageUp".to_string(),
+        Key::PageDown => "PageDown".to_string(),
+        Key::Tab => "Tab".to_string(),
+        Key::Backspace => "Backspace".to_string(),
+        Key::Delete => "Delete".to_string(),
+        Key::Insert => "Insert".to_string(),
+        Key::Enter => "Enter".to_string(),
+        Key::Esc => "Esc".to_string(),
+        Key::F(n) => format!("F{}", n),
+    };
+
+    let modifiers = [
+        (press.ctrl, "Ctrl-"),
+        (press.alt, "Alt-"),
+        (press.shift, "Shift-"),
+    ];
+    modifiers
+        .iter()
+        .filter(|(held, _)| *held)
+        .map(|(_, name)| *name)
+        .collect::<String>()
+        + &key
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::gui::{GuiEvent, Key, KeyPress};
+    use crate::runtime::keymap::{Action, Keymap, parse_event};
+
+    fn key(c: char) -> GuiEvent {
+        GuiEvent::Key(KeyPress::char(c))
+    }
+
+    #[test]
+    fn bound_actions() {
+        let keymap = Keymap::default();
+        assert_eq!(keymap.action(key('=')), Some(Action::SongEarlier));
+        assert_eq!(
+            keymap.action(GuiEvent::ScrollUp),
+            Some(Action::PreviousVerse)
+        );
+        assert_eq!(
+            keymap.action(GuiEvent::Key(KeyPress::ctrl('c'))),
+            Some(Action::Quit)
+        );
+        assert_eq!(keymap.action(key('x')), None);
+        assert_eq!(keymap.action(GuiEvent::Resize), None);
+    }
+
+    #[test]
+    fn parse_key_names() {
+        assert_eq!(parse_event("q"), Some(key('q')));
+        assert_eq!(parse_event("-"), Some(key('-')));
+        assert_eq!(parse_event("space"), Some(key(' ')));
+        assert_eq!(parse_event("shift-g"), Some(key('G')));
+        assert_eq!(
+            parse_event("ctrl-c"),
+            Some(GuiEvent::Key(KeyPress::ctrl('c')))
+        );
+        assert_eq!(parse_event("C--"), Some(GuiEvent::Key(KeyPress::ctrl('-'))));
+        assert_eq!(
+            parse_event("alt-shift-Left"),
+            Some(GuiEvent::Key(KeyPress {
+                alt: true,
+                shift: true,
+                ..KeyPress::plain(Key::Left)
+            }))
+        );
+        assert_eq!(
+            parse_event("f12"),
+            Some(GuiEvent::Key(KeyPress::plain(Key::F(12))))
+        );
+        assert_eq!(parse_event("wheel-up"), Some(GuiEvent::ScrollUp));
+        assert_eq!(parse_event("hyper-x"), None);
+        assert_eq!(parse_event("pgup"), None);
+    }
+
+    #[test]
+    fn rebind_keys() {
+        let keymap = Keymap::parse("quit=ctrl-q; next-verse=space down q; help=").unwrap();
+
+        assert_eq!(keymap.action(key('q')), Some(Action::NextVerse));
+        assert_eq!(keymap.action(key(' ')), Some(Action::NextVerse));
+        assert_eq!(keymap.action(key('j')), None);
+        assert_eq!(
+            keymap.action(GuiEvent::Key(KeyPress::ctrl('q'))),
+            Some(Action::Quit)
+        );
+        assert_eq!(keymap.action(GuiEvent::Key(KeyPress::ctrl('c'))), None);
+        assert_eq!(keymap.action(key('?')), None);
+
+        assert!(Keymap::parse("jump=j").is_err());
+        assert!(Keymap::parse("quit").is_err());
+        assert!(Keymap::parse("quit=ctrl-").is_err());
+    }
+
+    #[test]
+    fn help_lists_every_action() {
+        let help = Keymap::default().help();
+
+        assert_eq!(help.len(), Action::ALL.len());
+        assert_eq!(
+            help[0],
+            ("?, F1".to_string(), "Show or hide this help".to_string())
+        );
+        assert!(help.contains(&("q, Ctrl-c".to_string(), "Quit".to_string())));
+        assert!(help.contains(&(
+            "j, Down, Wheel down".to_string(),
+            "Browse to the next verse".to_string()
+        )));
+        assert!(help.contains(&(
+            "+, =".to_string(),
+            "Show the song lyric 100 ms earlier".to_string()
+        )));
+    }
+}
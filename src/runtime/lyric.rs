@@ -1,9 +1,8 @@
-use md5;
-use std::env;
-use std::fs::read_to_string;
+use std::fs::{read_to_string, rename, write};
 
 use crate::runtime::RuntimeError;
 use crate::runtime::cmus::PlayerSongInfo;
+use crate::runtime::config;
 
 type Result<T> = std::result::Result<T, RuntimeError>;
 
@@ -15,6 +14,22 @@ pub struct Verse {
 
 pub struct Lyric {
     verses: Vec<Verse>,
+    /* Milliseconds added to the song position, positive shows verses earlier */
+    offset: isize,
+    /* Path of the file the lyric was read from */
+    source: String,
+}
+
+/// Line of a lyric parsed in strict mode.
+pub struct StrictLine<'a> {
+    /// Every "[...]" group, parsed as a timestamp.
+    pub timestamps: Vec<(&'a str, Result<usize>)>,
+    /// Verse text, after the last group.
+    pub text: &'a str,
+    /// Text between two groups, ignored by the parsing.
+    pub ignored: Vec<&'a str>,
+    /// Whether the last "[" is not closed.
+    pub unclosed: bool,
 }
 
 enum LineParseState {
@@ -25,97 +40,302 @@ enum LineParseState {
 
 impl Lyric {
     pub fn new() -> Lyric {
-        Lyric { verses: Vec::new() }
+        Lyric {
+            verses: Vec::new(),
+            offset: 0,
+            source: String::new(),
+        }
+    }
+
+    pub fn offset(&self) -> isize {
+        self.offset
+    }
+
+    pub fn set_offset(&mut self, offset: isize) {
+        self.offset = offset;
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Timestamp of the verse at `index`.
+    pub fn timestamp(&self, index: usize) -> Option<usize> {
+        self.verses.get(index).map(|verse| verse.timestamp)
     }
 
     pub fn get_text(&self) -> Vec<&str> {
         self.verses.iter().map(|v| v.text.as_str()).collect()
     }
 
-    fn parse_timestamp(timestamp: &str) -> Result<usize> {
-        let mut minutes: usize = timestamp[0..2].parse()?;
-        let mut seconds: usize = timestamp[3..5].parse()?;
-        let mut milli: usize = timestamp[6..8].parse()?;
+    pub fn parse_timestamp(timestamp: &str) -> Result<usize> {
+        let field = |range: std::ops::Range<usize>| {
+            timestamp.get(range).ok_or(RuntimeError::ParseError(format!(
+                "timestamp '{}' too short",
+                timestamp
+            )))
+        };
+
+        let mut minutes: usize = field(0..2)?.parse()?;
+        let mut seconds: usize = field(3..5)?.parse()?;
+        let mut milli: usize = field(6..8)?.parse()?;
 
-        minutes = minutes * 60 * 1000;
-        seconds = seconds * 1000;
-        milli = milli * 10;
+        minutes *= 60 * 1000;
+        seconds *= 1000;
+        milli *= 10;
 
         /* Timestamp in milliseconds */
 
         Ok(minutes + seconds + milli)
     }
 
-    pub fn parse(&mut self, song: &PlayerSongInfo) -> Result<()> {
-        let lyric_folder = env::var("LYRICS_DIR")
-            .map_err(|_| RuntimeError::LyricDirEnvNotSet)?
-            .trim_end_matches('/')
-            .to_string();
+    /// Format milliseconds as a "mm:ss.xx" timestamp.
+    pub fn format_timestamp(timestamp: usize) -> String {
+        let minutes = timestamp / 60000;
+        let seconds = (timestamp % 60000) / 1000;
+        let centis = (timestamp % 1000) / 10;
+        format!("{:02}:{:02}.{:02}", minutes, seconds, centis)
+    }
+
+    /// Directories where the lyrics are looked up, in order.
+    pub fn lyrics_dirs() -> Result<Vec<String>> {
+        let dirs: Vec<String> = config::get()
+            .lyrics_dirs
+            .iter()
+            .map(|dir| dir.trim_end_matches('/').to_string())
+            .collect();
+
+        match dirs.is_empty() {
+            true => Err(RuntimeError::LyricDirEnvNotSet),
+            false => Ok(dirs),
+        }
+    }
+
+    /// MD5 digest of "<artist><title>", used to name lyric files.
+    pub fn digest(artist: &str, title: &str) -> String {
+        let digest = md5::compute(format!("{}{}", artist, title).as_bytes());
+        format!("{:x}", digest)
+    }
+
+    /// Sidecar lyric of `song`: its audio file with the `.lrc` extension.
+    pub fn sidecar(song: &PlayerSongInfo) -> Option<String> {
+        if song.file.is_empty() {
+            return None;
+        }
+
+        let sidecar = std::path::Path::new(&song.file).with_extension("lrc");
+        Some(sidecar.to_string_lossy().to_string())
+    }
+
+    /// Paths the lyric of `song` is looked up at, one per directory, then
+    /// the sidecar lyric.
+    pub fn paths(song: &PlayerSongInfo) -> Result<Vec<String>> {
+        Ok(Lyric::paths_in(&Lyric::lyrics_dirs()?, song))
+    }
+
+    /// Paths the lyric of `song` is looked up at, one per directory of
+    /// `dirs`, then the sidecar lyric.
+    pub fn paths_in(dirs: &[String], song: &PlayerSongInfo) -> Vec<String> {
+        let filename = format!("{}.lrc", Lyric::digest(&song.artist, &song.title));
+        let mut paths: Vec<String> = dirs
+            .iter()
+            .map(|dir| format!("{}/{}", dir, filename))
+            .collect();
+        paths.extend(Lyric::sidecar(song));
+
+        paths
+    }
+
+    /// Path of the lyric of `song` in the first location having it, or in
+    /// the first directory when none has it.
+    pub fn path(song: &PlayerSongInfo) -> Result<String> {
+        let paths = Lyric::paths(song)?;
+
+        let found = paths
+            .iter()
+            .find(|path| std::path::Path::new(path).exists());
+        Ok(found.unwrap_or(&paths[0]).clone())
+    }
 
-        let digest = md5::compute(format!("{}{}", song.artist, song.title).as_bytes());
+    /// Write `content` to `destination` through a temporary file, so the
+    /// runtime watching the lyrics directory never reads a partial lyric.
+    pub fn write(destination: &str, content: &[u8]) -> Result<()> {
+        let temporary = format!("{}.part", destination);
+        write(&temporary, content)?;
+        rename(&temporary, destination)?;
+        Ok(())
+    }
 
-        let filename = format!("{:x}.lrc", digest);
-        let filepath = lyric_folder + "/" + &filename;
+    pub fn parse(&mut self, song: &PlayerSongInfo) -> Result<()> {
+        let filepath = Lyric::path(song)?;
 
         /* Parse the file */
 
-        let file_content = match read_to_string(filepath) {
+        let file_content = match read_to_string(&filepath) {
             Ok(content) => content,
             Err(_) => return Err(RuntimeError::LyricNotFound),
         };
 
+        self.parse_content(&file_content);
+        self.source = filepath;
+
+        Ok(())
+    }
+
+    /// Replace the verses and offset with those of the lyric `content`.
+    pub fn parse_content(&mut self, content: &str) {
         self.verses.clear();
+        self.offset = Lyric::find_tag(content, "offset")
+            .and_then(|offset| offset.parse().ok())
+            .unwrap_or(0);
 
-        for line in file_content.lines() {
+        for line in content.lines() {
             self.parse_line(line);
         }
 
         self.verses.sort();
+    }
 
-        Ok(())
+    /// Store `offset` in the "[offset:]" tag of the lyric file of `song`.
+    pub fn save_offset(song: &PlayerSongInfo, offset: isize) -> Result<String> {
+        let filepath = Lyric::path(song)?;
+        let content = read_to_string(&filepath).map_err(|_| RuntimeError::LyricNotFound)?;
+
+        Lyric::write(
+            &filepath,
+            Lyric::set_offset_tag(&content, offset).as_bytes(),
+        )?;
+        Ok(filepath)
     }
 
-    fn parse_line_timestamps(line: &str) -> Vec<usize> {
+    /// Replace the "[offset:]" tag of the lyric content, adding it after the
+    /// leading tags if missing, or removing it when `offset` is zero.
+    fn set_offset_tag(content: &str, offset: isize) -> String {
+        let (bom, content) = match content.strip_prefix('\u{feff}') {
+            Some(content) => ("\u{feff}", content),
+            None => ("", content),
+        };
+
+        let is_offset = |line: &str| {
+            Lyric::parse_line_tag(line).is_some_and(|(key, _)| key.eq_ignore_ascii_case("offset"))
+        };
+
+        let mut lines: Vec<String> = Vec::new();
+        let mut tag_end = 0;
+        let mut replaced = false;
+
+        for line in content.lines() {
+            if is_offset(line) {
+                if !replaced && offset != 0 {
+                    lines.push(format!("[offset:{}]", offset));
+                }
+                replaced = true;
+                continue;
+            }
+
+            if Lyric::parse_line_tag(line).is_some() && tag_end == lines.len() {
+                tag_end += 1;
+            }
+            lines.push(line.to_string());
+        }
+
+        if !replaced && offset != 0 {
+            lines.insert(tag_end, format!("[offset:{}]", offset));
+        }
+
+        format!("{}{}\n", bom, lines.join("\n"))
+    }
+
+    /// Whether the lyric content has at least one timestamped verse.
+    pub fn is_synced(content: &str) -> bool {
+        content
+            .lines()
+            .any(|line| !Lyric::parse_line_timestamps(line.trim()).is_empty())
+    }
+
+    pub fn parse_line_timestamps(line: &str) -> Vec<usize> {
+        Lyric::parse_line_strict(line)
+            .timestamps
+            .into_iter()
+            .filter_map(|(_, timestamp)| timestamp.ok())
+            .collect()
+    }
+
+    /// Parse `line` as the lyric is parsed, keeping what the lenient parsing
+    /// drops silently: the "[...]" groups that are not timestamps, the text
+    /// between them and a "[" left open.
+    pub fn parse_line_strict(line: &str) -> StrictLine<'_> {
         let mut state: LineParseState = LineParseState::ParseInit;
-        let mut buff: String = String::new();
-        let mut timestamps: Vec<usize> = Vec::new();
+        let mut parsed = StrictLine {
+            timestamps: Vec::new(),
+            text: Lyric::parse_line_text(line),
+            ignored: Vec::new(),
+            unclosed: false,
+        };
+        /* Start of the group or of the text outside the groups */
+        let mut begin = 0;
+        /* Text before the group being read, ignored once the group closes */
+        let mut before = "";
 
-        buff.clear();
-        for char in line.chars() {
+        for (index, char) in line.char_indices() {
             match state {
                 LineParseState::ParseInit => {
                     if char == '[' {
                         state = LineParseState::InsideSquareBracket;
+                        begin = index + 1;
                     } else {
                         break;
                     };
                 }
                 LineParseState::InsideSquareBracket => {
-                    if char != ']' {
-                        buff.push(char);
-                    } else {
-                        match Lyric::parse_timestamp(buff.as_str()) {
-                            Ok(res) => {
-                                timestamps.push(res);
-                            }
-                            Err(_) => {}
-                        };
-                        buff.clear();
+                    if char == ']' {
+                        if !before.is_empty() {
+                            parsed.ignored.push(before);
+                        }
+                        let group = &line[begin..index];
+                        parsed
+                            .timestamps
+                            .push((group, Lyric::parse_timestamp(group)));
                         state = LineParseState::OutsideSquareBracket;
+                        begin = index + 1;
                     }
                 }
                 LineParseState::OutsideSquareBracket => {
                     if char == '[' {
+                        before = line[begin..index].trim();
                         state = LineParseState::InsideSquareBracket;
+                        begin = index + 1;
                     }
                 }
             };
         }
 
-        timestamps
+        parsed.unclosed = matches!(state, LineParseState::InsideSquareBracket);
+        parsed
     }
 
-    fn parse_line_text(line: &str) -> &str {
+    /// Split an ID tag line such as "[ar:Artist]" into its key and value.
+    pub fn parse_line_tag(line: &str) -> Option<(&str, &str)> {
+        let content = line.trim().strip_prefix('[')?.strip_suffix(']')?;
+        let (key, value) = content.split_once(':')?;
+
+        if key.is_empty() || !key.chars().all(|c| c.is_ascii_alphabetic()) {
+            return None;
+        }
+
+        Some((key, value.trim()))
+    }
+
+    /// Value of the first `key` ID tag found in the lyric content.
+    pub fn find_tag<'a>(content: &'a str, key: &str) -> Option<&'a str> {
+        content
+            .lines()
+            .filter_map(|line| Lyric::parse_line_tag(line.trim_start_matches('\u{feff}')))
+            .find(|(tag, _)| tag.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    pub fn parse_line_text(line: &str) -> &str {
         let text_begin: usize = match line.rfind(']') {
             Some(index) => index + 1,
             None => return "",
@@ -153,18 +373,37 @@ impl Lyric {
                 if i == 0 {
                     break;
                 }
-                i = i - 1;
+                i -= 1;
                 break;
             }
-            i = i + 1;
+            i += 1;
+        }
+
+        /* Past the last timestamp the last verse is being sung */
+        if i == self.verses.len() {
+            i = i.saturating_sub(1);
         }
+
         i
     }
+
+    /// Index of the verse sung at `position`, `None` during an instrumental
+    /// gap: before the first verse or on a verse without text.
+    pub fn sung_verse(&self, position: usize) -> Option<usize> {
+        let first = self.verses.first()?;
+        if position < first.timestamp {
+            return None;
+        }
+
+        let index = self.get_singed_verse_index(position);
+        (!self.verses[index].text.trim().is_empty()).then_some(index)
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
+    use crate::runtime::cmus::PlayerSongInfo;
     use crate::runtime::lyric::Lyric;
 
     #[test]
@@ -210,9 +449,99 @@ mod tests {
         let verse_text: &str = Lyric::parse_line_text(line);
         assert!(timestamps.is_empty());
         assert_eq!(verse_text, "abcd");
+
+        let line: &str = "[00:34] [x] [ü] abcd";
+        let timestamps: Vec<usize> = Lyric::parse_line_timestamps(line);
+        assert!(timestamps.is_empty());
+    }
+
+    #[test]
+    fn id_tags() {
+        assert_eq!(
+            Lyric::parse_line_tag("[ar:Sleep Token]"),
+            Some(("ar", "Sleep Token"))
+        );
+        assert_eq!(
+            Lyric::parse_line_tag(" [ti: Granite ] "),
+            Some(("ti", "Granite"))
+        );
+        assert_eq!(Lyric::parse_line_tag("[00:34.88] verse"), None);
+        assert_eq!(Lyric::parse_line_tag("[00:34.88]"), None);
+        assert_eq!(Lyric::parse_line_tag("[ar:Sleep Token"), None);
+
+        let content = "\u{feff}[ti:Granite]\n[AR:Sleep Token]\n[00:01.00] verse";
+        assert_eq!(Lyric::find_tag(content, "ar"), Some("Sleep Token"));
+        assert_eq!(Lyric::find_tag(content, "ti"), Some("Granite"));
+        assert_eq!(Lyric::find_tag(content, "al"), None);
     }
 
     #[test]
+    fn sung_verse_and_gaps() {
+        let mut lyric = Lyric::new();
+        lyric.parse_content("[00:05.00] first\n[00:10.00]\n[00:15.00] second");
+
+        assert_eq!(lyric.sung_verse(1000), None);
+        assert_eq!(lyric.sung_verse(5000), Some(0));
+        assert_eq!(lyric.sung_verse(12000), None);
+        assert_eq!(lyric.sung_verse(60000), Some(2));
+
+        lyric.parse_content("[ar:Artist]");
+        assert_eq!(lyric.sung_verse(5000), None);
+    }
+
+    #[test]
+    fn timestamp_format() {
+        assert_eq!(Lyric::format_timestamp(34880), "00:34.88");
+        assert_eq!(
+            Lyric::format_timestamp(10 * 60000 + 59 * 1000 + 675),
+            "10:59.67"
+        );
+        assert_eq!(
+            Lyric::parse_timestamp(&Lyric::format_timestamp(82330)),
+            Ok(82330)
+        );
+    }
+
+    #[test]
+    fn offset_tag() {
+        let content = "[ar:Artist]\n[ti:Title]\n[00:01.00] verse";
+        assert_eq!(
+            Lyric::set_offset_tag(content, -250),
+            "[ar:Artist]\n[ti:Title]\n[offset:-250]\n[00:01.00] verse\n"
+        );
+
+        let content = "\u{feff}[00:01.00] verse\n[offset:100]\n[offset:200]\n";
+        assert_eq!(
+            Lyric::set_offset_tag(content, 300),
+            "\u{feff}[00:01.00] verse\n[offset:300]\n"
+        );
+        assert_eq!(
+            Lyric::set_offset_tag(content, 0),
+            "\u{feff}[00:01.00] verse\n"
+        );
+    }
+
+    #[test]
+    fn singed_verse_index() {
+        let mut lyric = Lyric::new();
+        lyric.parse_line("[00:01.00] one");
+        lyric.parse_line("[00:02.00] two");
+
+        assert_eq!(lyric.get_singed_verse_index(0), 0);
+        assert_eq!(lyric.get_singed_verse_index(1500), 0);
+        assert_eq!(lyric.get_singed_verse_index(2000), 1);
+        assert_eq!(lyric.get_singed_verse_index(60000), 1);
+        assert_eq!(Lyric::new().get_singed_verse_index(60000), 0);
+    }
+
+    #[test]
+    fn filename_digest() {
+        let digest = Lyric::digest("Linkin Park", "One Step Closer");
+        assert_eq!(digest, "cc4cfe6eeafe0094b364876717b6f49f");
+    }
+
+    #[test]
+    #[allow(clippy::identity_op)]
     fn multi_timestamp() {
         let line: &str = "[00:34.88][01:22.33] [10:59.67] This is a verse";
         let timestamps: Vec<usize> = Lyric::parse_line_timestamps(line);
@@ -228,4 +557,30 @@ mod tests {
         );
         assert_eq!(verse_text, "This is a verse");
     }
+
+    #[test]
+    fn strict_line() {
+        let parsed = Lyric::parse_line_strict("[00:01.00] text [0:2] verse [00:03.00");
+        assert_eq!(parsed.timestamps.len(), 2);
+        assert!(parsed.timestamps[0].1.is_ok());
+        assert!(parsed.timestamps[1].1.is_err());
+        assert_eq!(parsed.ignored, vec!["text"]);
+        assert!(parsed.unclosed);
+
+        let parsed = Lyric::parse_line_strict("plain [00:01.00]");
+        assert!(parsed.timestamps.is_empty());
+        assert!(!parsed.unclosed);
+    }
+
+    #[test]
+    fn sidecar_path() {
+        let mut song = PlayerSongInfo::new();
+        assert_eq!(Lyric::sidecar(&song), None);
+
+        song.file = "/music/Hybrid Theory/01 Papercut.flac".to_string();
+        assert_eq!(
+            Lyric::sidecar(&song).as_deref(),
+            Some("/music/Hybrid Theory/01 Papercut.lrc")
+        );
+    }
 }
@@ -0,0 +1,332 @@
+use std::io::{Error, Result};
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::thread;
+
+use crate::runtime::dbus::{Bus, Value};
+use crate::runtime::error::RuntimeError;
+use crate::runtime::sink::{Event, Sink, State};
+
+/// Events desktop notifications are sent on.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum NotifyMode {
+    Off,
+    /// The title of each new song, with its first verse.
+    Song,
+    /// Every verse sung, replacing the notification of the previous one.
+    Verse,
+}
+
+impl NotifyMode {
+    pub fn parse(name: &str) -> Option<NotifyMode> {
+        match name {
+            "off" => Some(NotifyMode::Off),
+            "song" => Some(NotifyMode::Song),
+            "verse" => Some(NotifyMode::Verse),
+            _ => None,
+        }
+    }
+}
+
+/// Notification to send, `song` telling whether it is the first one of a
+/// song.
+struct Notification {
+    summary: String,
+    body: String,
+    song: bool,
+}
+
+/// Sender of freedesktop notifications, kept in a single notification
+/// replaced in place. The notifications are sent by a worker thread, so a
+/// slow or missing notification server never holds up the runtime.
+pub struct Notifier {
+    mode: NotifyMode,
+    notifications: Sender<Notification>,
+    /* Failures of the worker, reported on the next event */
+    failures: Receiver<Error>,
+}
+
+impl Notifier {
+    pub fn new(mode: NotifyMode, timeout: i32) -> Notifier {
+        Notifier::spawn(mode, timeout, Bus::session)
+    }
+
+    fn spawn(mode: NotifyMode, timeout: i32, connect: fn() -> Result<Bus>) -> Notifier {
+        let (notifications, pending) = channel();
+        let (failed, failures) = channel();
+
+        let worker = Worker {
+            timeout,
+            bus: None,
+            id: 0,
+            connect,
+            failed: false,
+        };
+        thread::spawn(move || worker.run(pending, failed));
+
+        Notifier {
+            mode,
+            notifications,
+            failures,
+        }
+    }
+
+    /// Notify `song`, described by its first verse in song mode and by the
+    /// verse being sung in verse mode.
+    pub fn song_changed(&mut self, song: &str, first: &str, sung: &str) {
+        match self.mode {
+            NotifyMode::Off => {}
+            NotifyMode::Song => self.notify(song, first, true),
+            NotifyMode::Verse => self.notify(song, sung, true),
+        }
+    }
+
+    /// Notify the verse being sung in verse mode, blank verses excepted.
+    pub fn verse_changed(&mut self, song: &str, sung: &str) {
+        if self.mode == NotifyMode::Verse && !sung.trim().is_empty() {
+            self.notify(song, sung, false);
+        }
+    }
+
+    fn notify(&mut self, summary: &str, body: &str, song: bool) {
+        /* The worker only stops once the notifier is dropped */
+        let _ = self.notifications.send(Notification {
+            summary: summary.to_string(),
+            body: body.to_string(),
+            song,
+        });
+    }
+
+    /// Failure of a notification sent earlier, if any.
+    pub fn failure(&self) -> Result<()> {
+        match self.failures.try_recv() {
+            Ok(error) => Err(error),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/* Connection to the notification server, owned by the worker thread */
+struct Worker {
+    /* Milliseconds before the notification expires, -1 for the server
+     * default */
+    timeout: i32,
+    /* Opened on the first notification, and again after a failure */
+    bus: Option<Bus>,
+    /* Notification to replace, zero for none */
+    id: u32,
+    connect: fn() -> Result<Bus>,
+    /* Whether the last notification failed, the next song being awaited
+     * before trying again */
+    failed: bool,
+}
+
+impl Worker {
+    fn run(mut self, pending: Receiver<Notification>, failed: Sender<Error>) {
+        while let Ok(mut notification) = pending.recv() {
+            /* Only the last of the notifications queued meanwhile is shown */
+            while let Ok(next) = pending.try_recv() {
+                notification = Notification {
+                    song: notification.song || next.song,
+                    ..next
+                };
+            }
+
+            if self.failed && !notification.song {
+                continue;
+            }
+            self.failed = false;
+
+            if let Err(error) = self.notify(&notification) {
+                self.bus = None;
+                self.failed = true;
+                let _ = failed.send(error);
+            }
+        }
+    }
+
+    fn notify(&mut self, notification: &Notification) -> Result<()> {
+        let bus = match &mut self.bus {
+            Some(bus) => bus,
+            None => self.bus.insert((self.connect)()?),
+        };
+
+        let reply = bus.call(
+            "org.freedesktop.Notifications",
+            "/org/freedesktop/Notifications",
+            "org.freedesktop.Notifications",
+            "Notify",
+            vec![
+                Value::Str("ciryl".to_string()),
+                Value::Uint32(self.id),
+                Value::Str(String::new()),
+                Value::Str(notification.summary.clone()),
+                Value::Str(notification.body.clone()),
+                Value::StrArray(Vec::new()),
+                Value::Dict(Vec::new()),
+                Value::Int32(self.timeout),
+            ],
+        )?;
+
+        if let Some(Value::Uint32(id)) = reply.first() {
+            self.id = *id;
+        }
+        Ok(())
+    }
+}
+
+impl Sink for Notifier {
+    fn event(&mut self, event: &Event) -> crate::runtime::Result<()> {
+        let song = |state: &State| format!("{} - {}", state.song.artist, state.song.title);
+
+        match event {
+            Event::SongChanged(state) => self.song_changed(
+                &song(state),
+                &state.first_verse(),
+                &state.verse(state.index),
+            ),
+            Event::VerseChanged { state, .. } => {
+                self.verse_changed(&song(state), &state.verse(state.index))
+            }
+            _ => {}
+        };
+
+        self.failure()
+            .map_err(|error| RuntimeError::NotifyError(error.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::dbus::{Bus, Message, Value};
+    use crate::runtime::notifier::{Notifier, NotifyMode};
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::os::unix::net::UnixListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::mpsc::{Receiver, channel};
+    use std::thread;
+    use std::time::Duration;
+
+    /* Socket of the mock daemon, unique to the test process */
+    fn socket() -> String {
+        let name = format!("ciryl-notifications-{}", std::process::id());
+        std::env::temp_dir()
+            .join(name)
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /* Notification daemon answering Hello and Notify on `socket()`, sending
+     * the body of every notification received */
+    fn mock_daemon() -> Receiver<Vec<Value>> {
+        let _ = std::fs::remove_file(socket());
+        let listener = UnixListener::bind(socket()).unwrap();
+        let (sender, receiver) = channel();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut nul = [0];
+            stream.read_exact(&mut nul).unwrap();
+
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut line = String::new();
+            reader.read_line(&mut line).unwrap();
+            assert!(line.starts_with("AUTH EXTERNAL "));
+            stream.write_all(b"OK 0123456789abcdef\r\n").unwrap();
+            line.clear();
+            reader.read_line(&mut line).unwrap();
+            assert_eq!(line, "BEGIN\r\n");
+
+            let mut next_id = 41;
+            while let Ok(call) = Message::read(&mut reader) {
+                let body = match call.member() {
+                    Some("Hello") => vec![Value::Str(":1.7".to_string())],
+                    Some("Notify") => {
+                        /* A new notification gets a new id */
+                        if call.body[1] == Value::Uint32(0) {
+                            next_id += 1;
+                        }
+                        sender.send(call.body.clone()).unwrap();
+                        vec![Value::Uint32(next_id)]
+                    }
+                    _ => Vec::new(),
+                };
+                let reply = Message::method_return(1, call.serial, body);
+                stream.write_all(&reply.encode()).unwrap();
+            }
+        });
+
+        receiver
+    }
+
+    #[test]
+    fn notify_in_place() {
+        let notifications = mock_daemon();
+        let mut notifier = Notifier::spawn(NotifyMode::Verse, 5000, || {
+            Bus::connect(&format!("unix:path={}", socket()))
+        });
+
+        notifier.song_changed("Linkin Park - Numb", "I'm tired", "I'm tired");
+        let first = notifications.recv().unwrap();
+        assert_eq!(first[0], Value::Str("ciryl".to_string()));
+        assert_eq!(first[1], Value::Uint32(0));
+        assert_eq!(first[3], Value::Str("Linkin Park - Numb".to_string()));
+        assert_eq!(first[4], Value::Str("I'm tired".to_string()));
+        assert_eq!(first[7], Value::Int32(5000));
+
+        notifier.verse_changed("Linkin Park - Numb", "of being what you want me to be");
+        notifier.verse_changed("Linkin Park - Numb", " ");
+        let second = notifications.recv().unwrap();
+        assert_eq!(second[1], Value::Uint32(42));
+        assert_eq!(
+            second[4],
+            Value::Str("of being what you want me to be".to_string())
+        );
+        assert!(notifier.failure().is_ok());
+
+        /* The blank verse is not notified */
+        drop(notifier);
+        assert!(notifications.recv().is_err());
+        std::fs::remove_file(socket()).unwrap();
+    }
+
+    #[test]
+    fn retry_on_next_song() {
+        static ATTEMPTS: AtomicUsize = AtomicUsize::new(0);
+        let mut notifier = Notifier::spawn(NotifyMode::Verse, -1, || {
+            ATTEMPTS.fetch_add(1, Ordering::SeqCst);
+            Err(std::io::Error::other("no bus"))
+        });
+        let failure = |notifier: &Notifier| notifier.failures.recv_timeout(Duration::from_secs(5));
+
+        notifier.song_changed("Song", "First", "Sung");
+        assert_eq!(failure(&notifier).unwrap().to_string(), "no bus");
+
+        /* Verses are dropped until the next song */
+        notifier.verse_changed("Song", "Second");
+        notifier.verse_changed("Song", "Third");
+        notifier.song_changed("Next song", "First", "Sung");
+        assert!(failure(&notifier).is_ok());
+        assert_eq!(ATTEMPTS.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn song_mode() {
+        let mut notifier = Notifier::spawn(NotifyMode::Song, -1, || {
+            Err(std::io::Error::other("no bus"))
+        });
+
+        notifier.verse_changed("Song", "Sung");
+        assert!(notifier.failure().is_ok());
+        notifier.song_changed("Song", "First", "Sung");
+        assert!(
+            notifier
+                .failures
+                .recv_timeout(Duration::from_secs(5))
+                .is_ok()
+        );
+
+        assert_eq!(NotifyMode::parse("verse"), Some(NotifyMode::Verse));
+        assert_eq!(NotifyMode::parse("always"), None);
+    }
+}
@@ -0,0 +1,101 @@
+/// Search of a text among the verses of the lyric.
+pub struct Search {
+    pub query: String,
+    /// Whether the query is still being typed in the prompt.
+    pub editing: bool,
+    /* Verse viewed when the search started, the first match is looked from it */
+    origin: usize,
+}
+
+impl Search {
+    pub fn new(origin: usize) -> Search {
+        Search {
+            query: String::new(),
+            editing: true,
+            origin,
+        }
+    }
+
+    /// Indexes of the verses containing the query, ignoring case.
+    pub fn matches(&self, verses: &[&str]) -> Vec<usize> {
+        if self.query.is_empty() {
+            return Vec::new();
+        }
+
+        let query = self.query.to_lowercase();
+        verses
+            .iter()
+            .enumerate()
+            .filter(|(_, verse)| verse.to_lowercase().contains(&query))
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// First match at or after the verse viewed when the search started,
+    /// wrapping around the end of the lyric.
+    pub fn first(&self, verses: &[&str]) -> Option<usize> {
+        let matches = self.matches(verses);
+        matches
+            .iter()
+            .find(|index| **index >= self.origin)
+            .or(matches.first())
+            .copied()
+    }
+
+    /// Match after, or before when not `forward`, the verse at `from`,
+    /// wrapping around the lyric.
+    pub fn next(&self, verses: &[&str], from: usize, forward: bool) -> Option<usize> {
+        let matches = self.matches(verses);
+        match forward {
+            true => matches
+                .iter()
+                .find(|index| **index > from)
+                .or(matches.first()),
+            false => matches
+                .iter()
+                .rev()
+                .find(|index| **index < from)
+                .or(matches.last()),
+        }
+        .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::search::Search;
+
+    const VERSES: [&str; 5] = ["Shut up", "when I'm talking", "to you", "shut UP", "I'm"];
+
+    fn search(query: &str, origin: usize) -> Search {
+        let mut search = Search::new(origin);
+        search.query = query.to_string();
+        search
+    }
+
+    #[test]
+    fn case_insensitive_matches() {
+        assert_eq!(search("shut", 0).matches(&VERSES), vec![0, 3]);
+        assert_eq!(search("i'm", 0).matches(&VERSES), vec![1, 4]);
+        assert!(search("", 0).matches(&VERSES).is_empty());
+        assert!(search("closer", 0).matches(&VERSES).is_empty());
+    }
+
+    #[test]
+    fn first_match_from_origin() {
+        assert_eq!(search("shut", 2).first(&VERSES), Some(3));
+        assert_eq!(search("shut", 3).first(&VERSES), Some(3));
+        assert_eq!(search("shut", 4).first(&VERSES), Some(0));
+        assert_eq!(search("closer", 0).first(&VERSES), None);
+    }
+
+    #[test]
+    fn cycle_matches() {
+        let search = search("shut", 0);
+        assert_eq!(search.next(&VERSES, 0, true), Some(3));
+        assert_eq!(search.next(&VERSES, 3, true), Some(0));
+        assert_eq!(search.next(&VERSES, 3, false), Some(0));
+        assert_eq!(search.next(&VERSES, 0, false), Some(3));
+    }
+}
@@ -0,0 +1,244 @@
+use crate::runtime::cmus::PlayerSongInfo;
+use crate::runtime::lyric::Lyric;
+use crate::runtime::notifier::{Notifier, NotifyMode};
+use crate::runtime::{Result, RuntimeUpdate, config};
+
+/// Song playing and its lyric, as seen by the sinks.
+#[derive(Clone, Copy)]
+pub struct State<'a> {
+    pub song: &'a PlayerSongInfo,
+    pub lyric: &'a Lyric,
+    /// Index of the verse being sung.
+    pub index: usize,
+    /// Milliseconds added to the position of every song.
+    pub global_offset: isize,
+}
+
+impl State<'_> {
+    /// Text of the verse at `index`, empty past the lyric.
+    pub fn verse(&self, index: usize) -> String {
+        self.lyric
+            .get_text()
+            .get(index)
+            .map(|verse| verse.to_string())
+            .unwrap_or_default()
+    }
+
+    /// First verse having some text, lyrics often opening on blank ones.
+    pub fn first_verse(&self) -> String {
+        self.lyric
+            .get_text()
+            .into_iter()
+            .find(|verse| !verse.trim().is_empty())
+            .unwrap_or_default()
+            .to_string()
+    }
+}
+
+/// Reason the lyric can't be followed.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PlayerError {
+    /// The player socket can't be reached.
+    PlayerUnreachable,
+    /// The status of the player doesn't describe a song.
+    SongUnparsable,
+    /// No lyrics directory is configured.
+    NoLyricsDir,
+}
+
+/// Event of the runtime, sent to every sink.
+#[derive(Clone, Copy)]
+pub enum Event<'a> {
+    /// A song with a lyric started.
+    SongChanged(State<'a>),
+    /// Another verse is sung, `previous` being the one sung before.
+    VerseChanged { state: State<'a>, previous: usize },
+    /// The position or the status of the song changed, the verse did not.
+    StateChanged(State<'a>),
+    /// The song started has no lyric.
+    LyricMissing(&'a PlayerSongInfo),
+    /// The lyric can't be followed.
+    PlayerError(PlayerError),
+}
+
+impl<'a> Event<'a> {
+    /// Event telling `update` of the song in `state`, `previous` being the
+    /// verse sung before it. Updates changing nothing give none.
+    pub(super) fn of(
+        update: RuntimeUpdate,
+        state: State<'a>,
+        previous: usize,
+    ) -> Option<Event<'a>> {
+        let event = match update {
+            RuntimeUpdate::NewSong => Event::SongChanged(state),
+            RuntimeUpdate::NewIndex => Event::VerseChanged { state, previous },
+            RuntimeUpdate::NewState => Event::StateChanged(state),
+            RuntimeUpdate::LyricNotFound => Event::LyricMissing(state.song),
+            RuntimeUpdate::CmusError => Event::PlayerError(PlayerError::PlayerUnreachable),
+            RuntimeUpdate::ParseError => Event::PlayerError(PlayerError::SongUnparsable),
+            RuntimeUpdate::LyricDirNotSet => Event::PlayerError(PlayerError::NoLyricsDir),
+            RuntimeUpdate::DisplayError | RuntimeUpdate::Nop => return None,
+        };
+
+        Some(event)
+    }
+}
+
+/// Output of the runtime, such as the TUI, stdout or desktop notifications.
+pub trait Sink {
+    fn event(&mut self, event: &Event) -> Result<()>;
+}
+
+/// Sinks combined, every one receiving each event even when another fails,
+/// the first failure being returned.
+impl Sink for Vec<Box<dyn Sink>> {
+    fn event(&mut self, event: &Event) -> Result<()> {
+        let mut result = Ok(());
+        for sink in self.iter_mut() {
+            let handled = sink.event(event);
+            if result.is_ok() {
+                result = handled;
+            }
+        }
+        result
+    }
+}
+
+/// Sinks enabled by the configuration, besides the main output of the
+/// command.
+pub fn from_config() -> Vec<Box<dyn Sink>> {
+    let mut sinks: Vec<Box<dyn Sink>> = Vec::new();
+
+    if config::get().notify != NotifyMode::Off {
+        sinks.push(Box::new(Notifier::new(
+            config::get().notify,
+            config::get().notify_timeout,
+        )));
+    }
+
+    sinks
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::RuntimeUpdate;
+    use crate::runtime::cmus::PlayerSongInfo;
+    use crate::runtime::error::RuntimeError;
+    use crate::runtime::lyric::Lyric;
+    use crate::runtime::sink::{Event, PlayerError, Sink, State};
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /* Sink writing down the events it receives in a shared log */
+    struct Recorder {
+        log: Rc<RefCell<Vec<String>>>,
+        fail: bool,
+    }
+
+    impl Sink for Recorder {
+        fn event(&mut self, event: &Event) -> crate::runtime::Result<()> {
+            let entry = match event {
+                Event::SongChanged(state) => format!("song {}", state.song.title),
+                Event::VerseChanged { state, previous } => {
+                    format!("verse {} -> {}", previous, state.verse(state.index))
+                }
+                Event::StateChanged(state) => format!("state {}", state.song.position),
+                Event::LyricMissing(song) => format!("missing {}", song.title),
+                Event::PlayerError(error) => format!("error {:?}", error),
+            };
+            self.log.borrow_mut().push(entry);
+
+            match self.fail {
+                true => Err(RuntimeError::GuiError("failed".to_string())),
+                false => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn events_of_updates() {
+        let mut song = PlayerSongInfo::new();
+        song.title = "Numb".to_string();
+        song.position = 1500;
+        let mut lyric = Lyric::new();
+        lyric.parse_content("[00:00.00] I'm tired\n[00:01.00] of being what you want me to be");
+        let state = State {
+            song: &song,
+            lyric: &lyric,
+            index: 1,
+            global_offset: 0,
+        };
+
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut recorder = Recorder {
+            log: log.clone(),
+            fail: false,
+        };
+        for update in [
+            RuntimeUpdate::NewSong,
+            RuntimeUpdate::NewIndex,
+            RuntimeUpdate::Nop,
+            RuntimeUpdate::NewState,
+            RuntimeUpdate::DisplayError,
+            RuntimeUpdate::LyricNotFound,
+            RuntimeUpdate::CmusError,
+        ] {
+            if let Some(event) = Event::of(update, state, 0) {
+                recorder.event(&event).unwrap();
+            }
+        }
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                "song Numb",
+                "verse 0 -> of being what you want me to be",
+                "state 1500",
+                "missing Numb",
+                "error PlayerUnreachable",
+            ]
+        );
+    }
+
+    #[test]
+    fn first_verse() {
+        let song = PlayerSongInfo::new();
+        let mut lyric = Lyric::new();
+        lyric.parse_content("[00:00.00]\n[00:02.00]  \n[00:04.00] Crawling in my skin");
+        let state = State {
+            song: &song,
+            lyric: &lyric,
+            index: 0,
+            global_offset: 0,
+        };
+        assert_eq!(state.first_verse(), "Crawling in my skin");
+
+        let lyric = Lyric::new();
+        let state = State {
+            lyric: &lyric,
+            ..state
+        };
+        assert_eq!(state.first_verse(), "");
+    }
+
+    #[test]
+    fn combined_sinks() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut sinks: Vec<Box<dyn Sink>> = vec![
+            Box::new(Recorder {
+                log: log.clone(),
+                fail: true,
+            }),
+            Box::new(Recorder {
+                log: log.clone(),
+                fail: false,
+            }),
+        ];
+
+        let result = sinks.event(&Event::PlayerError(PlayerError::SongUnparsable));
+
+        assert!(result.is_err());
+        assert_eq!(log.borrow().len(), 2);
+    }
+}
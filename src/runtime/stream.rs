@@ -0,0 +1,164 @@
+use serde_json::json;
+use std::io::{ErrorKind, Write, stdout};
+use std::thread::sleep;
+
+use crate::runtime::cmus::PlayerSongInfo;
+use crate::runtime::config;
+use crate::runtime::sink::{self, Event, Sink, State};
+use crate::runtime::{Result, RuntimeReturn, RuntimeUpdate, Tracker};
+
+/// Format of the lines written by the stream.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum StreamFormat {
+    /// The verse being sung, alone.
+    Plain,
+    /// An object with the song, the verse being sung and the next one.
+    Json,
+}
+
+/// Sink writing the verse being sung to stdout whenever it changes, for
+/// status bars such as waybar, polybar or tmux.
+struct Stdout {
+    format: StreamFormat,
+    /* Song, verse, status and second of the last line written, to skip
+     * repeating it */
+    written: Option<(String, String, Option<usize>, String, usize)>,
+    /* Whether the reader is gone */
+    closed: bool,
+}
+
+impl Stdout {
+    /// Line describing the verse at `index` of `state`, no verse when the
+    /// lyric is missing.
+    fn line(&self, song: &PlayerSongInfo, state: Option<&State>) -> String {
+        let verse = |index: Option<usize>| match (state, index) {
+            (Some(state), Some(index)) => state.verse(index),
+            _ => String::new(),
+        };
+        let index = state.map(|state| state.index);
+
+        match self.format {
+            StreamFormat::Plain => verse(index),
+            StreamFormat::Json => {
+                let progress = match song.duration {
+                    0 => 0,
+                    duration => song.position.min(duration) * 100 / duration,
+                };
+
+                json!({
+                    "artist": song.artist,
+                    "title": song.title,
+                    "album": song.album,
+                    "status": song.status,
+                    "lyric": index.is_some(),
+                    "verse": verse(index),
+                    "next": verse(index.map(|index| index + 1)),
+                    "position": song.position,
+                    "duration": song.duration,
+                    "progress": progress,
+                })
+                .to_string()
+            }
+        }
+    }
+
+    /// Write the line of `song` unless it was the last one written.
+    fn write(&mut self, song: &PlayerSongInfo, state: Option<&State>) -> Result<()> {
+        /* Only the JSON lines tell the status and the position */
+        let (status, second) = match self.format {
+            StreamFormat::Plain => (String::new(), 0),
+            StreamFormat::Json => (song.status.clone(), song.position / 1000),
+        };
+        let key = (
+            song.artist.clone(),
+            song.title.clone(),
+            state.map(|state| state.index),
+            status,
+            second,
+        );
+        if self.written.as_ref() == Some(&key) {
+            return Ok(());
+        }
+
+        let line = self.line(song, state);
+        let mut out = stdout().lock();
+        match writeln!(out, "{}", line).and_then(|_| out.flush()) {
+            Ok(_) => self.written = Some(key),
+            /* The reader is gone, nobody is left to stream to */
+            Err(error) if error.kind() == ErrorKind::BrokenPipe => self.closed = true,
+            Err(error) => return Err(error.into()),
+        }
+        Ok(())
+    }
+}
+
+impl Sink for Stdout {
+    fn event(&mut self, event: &Event) -> Result<()> {
+        match event {
+            Event::SongChanged(state)
+            | Event::VerseChanged { state, .. }
+            | Event::StateChanged(state) => self.write(state.song, Some(state)),
+            Event::LyricMissing(song) => self.write(song, None),
+            Event::PlayerError(_) => self.write(&PlayerSongInfo::new(), None),
+        }
+    }
+}
+
+/// Runtime streaming the verse being sung to stdout, along with the sinks of
+/// the configuration.
+pub struct StreamRuntime {
+    tracker: Tracker,
+    stdout: Stdout,
+    sinks: Vec<Box<dyn Sink>>,
+}
+
+impl StreamRuntime {
+    pub fn new(format: StreamFormat) -> Result<StreamRuntime> {
+        Ok(StreamRuntime {
+            tracker: Tracker::new()?,
+            stdout: Stdout {
+                format,
+                written: None,
+                closed: false,
+            },
+            sinks: sink::from_config(),
+        })
+    }
+
+    pub fn task(&mut self) -> Result<RuntimeReturn> {
+        let mut sinks = StdoutSinks {
+            stdout: &mut self.stdout,
+            sinks: &mut self.sinks,
+        };
+        let update = self.tracker.follow(&mut sinks)?;
+
+        if self.stdout.closed {
+            return Ok(RuntimeReturn::Exit);
+        }
+
+        /* Look the song up again once cmus is back */
+        if update == RuntimeUpdate::CmusError {
+            self.tracker.song = PlayerSongInfo::new();
+        }
+
+        sleep(config::get().tick);
+        Ok(RuntimeReturn::Continue)
+    }
+}
+
+/* Stdout first, then the other sinks, whose failures are only reported */
+struct StdoutSinks<'a> {
+    stdout: &'a mut Stdout,
+    sinks: &'a mut Vec<Box<dyn Sink>>,
+}
+
+impl Sink for StdoutSinks<'_> {
+    fn event(&mut self, event: &Event) -> Result<()> {
+        self.stdout.event(event)?;
+
+        if let Err(error) = self.sinks.event(event) {
+            eprintln!("ciryl: {}", error);
+        }
+        Ok(())
+    }
+}
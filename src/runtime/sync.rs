@@ -0,0 +1,144 @@
+use crate::runtime::cmus::{Cmus, PlayerSongInfo};
+use crate::runtime::config;
+use crate::runtime::editor::SyncEditor;
+use crate::runtime::gui::theme::Theme;
+use crate::runtime::gui::{Frame, Gui, GuiEvent, KeyPress};
+use crate::runtime::lyric::Lyric;
+use crate::runtime::{Result, RuntimeError, RuntimeReturn};
+
+/// Runtime of the editor used to synchronize a plain-text lyric with the
+/// song playing on cmus.
+pub struct SyncRuntime {
+    player: Cmus,
+    editor: SyncEditor,
+    song: Option<PlayerSongInfo>,
+    initialized: bool,
+    redraw: bool,
+    message: String,
+}
+
+impl SyncRuntime {
+    pub fn new(text: &str) -> Result<SyncRuntime> {
+        let editor = SyncEditor::new(text);
+        if editor.is_empty() {
+            return Err(RuntimeError::UsageError(
+                "the lyric file has no lines to synchronize".to_string(),
+            ));
+        }
+
+        Ok(SyncRuntime {
+            player: Cmus::new()?,
+            editor,
+            song: None,
+            initialized: false,
+            redraw: true,
+            message: String::new(),
+        })
+    }
+
+    fn playing_song(&mut self) -> Option<PlayerSongInfo> {
+        self.player.update().ok()?;
+        self.player.playing_song_metadata().ok()
+    }
+
+    fn status_line(&self, playing: &Option<PlayerSongInfo>) -> String {
+        let position = match playing {
+            Some(song) => Lyric::format_timestamp(song.position),
+            None => "--:--.--".to_string(),
+        };
+
+        /* Once stamped, the lyric belongs to the song playing at that time */
+        let song = match (&self.song, playing) {
+            (Some(song), _) | (None, Some(song)) => format!("{} - {}", song.artist, song.title),
+            (None, None) => "cmus not playing".to_string(),
+        };
+
+        format!(
+            "SYNC {} | {} | space: stamp  +/-: nudge  u: undo  j/k: move  w: save  q: quit | {}",
+            position, song, self.message
+        )
+    }
+
+    fn save(&self, playing: &Option<PlayerSongInfo>) -> String {
+        if !self.editor.is_stamped() {
+            return "Nothing stamped yet".to_string();
+        }
+        /* A lyric loaded stamped belongs to the song playing when saved */
+        let Some(song) = self.song.as_ref().or(playing.as_ref()) else {
+            return "Can't save: cmus not playing".to_string();
+        };
+
+        let lrc = self.editor.to_lrc(&song.artist, &song.title);
+        match Lyric::path(song).and_then(|path| Lyric::write(&path, lrc.as_bytes()).map(|_| path)) {
+            Ok(path) => format!("Saved to {}", path),
+            Err(error) => format!("Can't save: {}", error),
+        }
+    }
+
+    pub fn task(&mut self) -> Result<RuntimeReturn> {
+        let result = self.step();
+        /* The error is printed once the terminal is usable again */
+        if result.is_err() {
+            Gui::restore();
+        }
+        result
+    }
+
+    fn step(&mut self) -> Result<RuntimeReturn> {
+        if !self.initialized {
+            Gui::initialize(Theme::from_config()?)?;
+            self.initialized = true;
+        }
+
+        let playing = self.playing_song();
+
+        if self.redraw {
+            let lines = self.editor.display_lines();
+            let lines: Vec<&str> = lines.iter().map(String::as_str).collect();
+            Gui::clear_screen()?;
+            Gui::print_vector(
+                &lines,
+                &Frame {
+                    fixed_index: self.editor.current(),
+                    ..Frame::default()
+                },
+            )?;
+            self.redraw = false;
+        }
+        Gui::print_footer(&self.status_line(&playing))?;
+
+        if let Some(event) = Gui::pool_event(config::get().tick)? {
+            /* Ctrl-C and the termination signals quit as 'q' does */
+            let key = match event {
+                GuiEvent::Key(press) if press == KeyPress::ctrl('c') => Some('q'),
+                GuiEvent::Key(press) => press.text(),
+                GuiEvent::Terminate => Some('q'),
+                _ => None,
+            };
+
+            match key {
+                /* Press space to stamp the current line with the song position */
+                Some(' ') => {
+                    if let Ok(song) = self.player.playing_song_metadata() {
+                        self.editor.stamp(song.position);
+                        self.song.get_or_insert(song);
+                    }
+                }
+                Some('+' | '=') => self.editor.nudge(SyncEditor::NUDGE_STEP),
+                Some('-') => self.editor.nudge(-SyncEditor::NUDGE_STEP),
+                Some('u') => self.editor.undo(),
+                Some('j') => self.editor.move_down(),
+                Some('k') => self.editor.move_up(),
+                Some('w') => self.message = self.save(&playing),
+                Some('q') => {
+                    Gui::terminate()?;
+                    return Ok(RuntimeReturn::Exit);
+                }
+                _ => {}
+            };
+            self.redraw = true;
+        }
+
+        Ok(RuntimeReturn::Continue)
+    }
+}
@@ -0,0 +1,384 @@
+use std::time::{Duration, Instant};
+
+use crate::runtime::Result;
+use crate::runtime::bars::Bars;
+use crate::runtime::config;
+use crate::runtime::gui::{Frame, Gui, Key, KeyPress, Transition};
+use crate::runtime::keymap::Keymap;
+use crate::runtime::lyric::Lyric;
+use crate::runtime::search::Search;
+use crate::runtime::sink::{Event, PlayerError, Sink, State};
+
+/* What the screen shows above the status row */
+enum Screen {
+    Lyric,
+    LyricMissing { artist: String, title: String },
+    Error(String),
+}
+
+/// Terminal output of the runtime: the lyric following the song with its
+/// bars and status row, and the view browsing and searching it.
+pub struct Tui {
+    screen: Screen,
+    pub message: String,
+    pub redraw_status: bool,
+    bars: Bars,
+    /* Duration of the scrolling between verses, zero to jump at once */
+    animation: Duration,
+    /* Verse scrolled from and when the scrolling started */
+    transition: Option<(usize, Instant)>,
+    /* Verse the view is detached on while browsing, and the last move */
+    pub browse: Option<(usize, Instant)>,
+    pub search: Option<Search>,
+    pub keymap: Keymap,
+    /* Whether the keybindings are listed over the lyric */
+    pub help: bool,
+}
+
+impl Tui {
+    pub fn new() -> Tui {
+        Tui {
+            screen: Screen::Lyric,
+            message: String::new(),
+            redraw_status: false,
+            bars: Bars::from_config(),
+            animation: config::get().animation,
+            transition: None,
+            browse: None,
+            search: None,
+            keymap: Keymap::default(),
+            help: false,
+        }
+    }
+
+    /// Whether the lyric is on screen, rather than an error.
+    pub fn showing_lyric(&self) -> bool {
+        matches!(self.screen, Screen::Lyric)
+    }
+
+    /// Whether the search prompt takes the keys typed.
+    pub fn editing(&self) -> bool {
+        self.search.as_ref().is_some_and(|search| search.editing)
+    }
+
+    /// Time to wait for an event, shorter while scrolling.
+    pub fn timeout(&self) -> Duration {
+        match self.transition {
+            Some(_) => Gui::FRAME,
+            None => config::get().tick,
+        }
+    }
+
+    /// Progress of the scrolling to the active verse, `None` once done.
+    fn transition(&self) -> Option<Transition> {
+        let (previous, started) = self.transition?;
+        let progress = started.elapsed().as_secs_f32() / self.animation.as_secs_f32();
+
+        (progress < 1.0).then_some(Transition { previous, progress })
+    }
+
+    /// Verse the view is centered on while browsing.
+    pub fn view(&self) -> Option<usize> {
+        self.browse.map(|(view, _)| view)
+    }
+
+    fn status_line(&self, state: &State) -> String {
+        match (&self.search, self.browse) {
+            (Some(search), _) if search.editing => format!("/{}", search.query),
+            (Some(search), _) if search.matches(&state.lyric.get_text()).is_empty() => {
+                format!("Pattern not found: {}", search.query)
+            }
+            (Some(_), Some(_)) => {
+                "Following paused, n/N: next/previous match, Enter: seek, Esc: resume".to_string()
+            }
+            (None, Some(_)) => "Following paused, Enter: seek, Esc: resume".to_string(),
+            _ => self.message.clone(),
+        }
+    }
+
+    /// Detach the view from the active verse and move it `delta` verses.
+    pub fn browse(&mut self, state: &State, delta: isize) -> Result<()> {
+        let view = self
+            .view()
+            .unwrap_or(state.index)
+            .saturating_add_signed(delta);
+        self.browse_to(state, view)
+    }
+
+    /// Detach the view from the active verse and move it to `view`.
+    fn browse_to(&mut self, state: &State, view: usize) -> Result<()> {
+        /* Browsing is possible only while the lyric is shown */
+        if !self.showing_lyric() {
+            return Ok(());
+        }
+
+        let last = state.lyric.get_text().len().saturating_sub(1);
+        self.browse = Some((view.min(last), Instant::now()));
+        self.transition = None;
+        self.redraw_lyric(state)
+    }
+
+    /// Move the view to the next or previous match of the search.
+    pub fn browse_match(&mut self, state: &State, forward: bool) -> Result<()> {
+        let from = self.view().unwrap_or(state.index);
+        let found = self
+            .search
+            .as_ref()
+            .and_then(|search| search.next(&state.lyric.get_text(), from, forward));
+
+        match found {
+            Some(view) => self.browse_to(state, view),
+            None => Ok(()),
+        }
+    }
+
+    /// Handle the keys typed in the search prompt, moving the view to the
+    /// first match as the query changes.
+    pub fn search_input(&mut self, state: &State, press: KeyPress) -> Result<()> {
+        let Some(search) = &mut self.search else {
+            return Ok(());
+        };
+
+        match (press.text(), press.key) {
+            (Some(c), _) => search.query.push(c),
+            (None, Key::Backspace) => {
+                search.query.pop();
+            }
+            (None, Key::Enter) => search.editing = false,
+            (None, Key::Esc) => return self.clear_search(state),
+            _ => return Ok(()),
+        }
+
+        self.redraw_status = true;
+        match search.first(&state.lyric.get_text()) {
+            Some(view) => self.browse_to(state, view),
+            None => self.redraw_lyric(state),
+        }
+    }
+
+    /// Remove the search highlights and follow the song again.
+    pub fn clear_search(&mut self, state: &State) -> Result<()> {
+        self.search = None;
+        self.browse = None;
+        self.redraw_lyric(state)
+    }
+
+    /// Attach the view back to the active verse.
+    fn follow(&mut self, state: &State) -> Result<()> {
+        if self.browse.take().is_some() {
+            self.redraw_lyric(state)?;
+        }
+        Ok(())
+    }
+
+    pub fn redraw_lyric(&mut self, state: &State) -> Result<()> {
+        if !self.showing_lyric() {
+            return Ok(());
+        }
+
+        self.print_lyric(state, None)?;
+        self.redraw_status = true;
+        Ok(())
+    }
+
+    fn print_lyric(&self, state: &State, transition: Option<Transition>) -> Result<()> {
+        let verses = state.lyric.get_text();
+        let matches = match &self.search {
+            Some(search) => search.matches(&verses),
+            None => Vec::new(),
+        };
+
+        Gui::print_vector(
+            &verses,
+            &Frame {
+                fixed_index: state.index,
+                transition,
+                view: self.view(),
+                matches: &matches,
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Value of the header and footer field `name`.
+    fn bar_field(state: &State, name: &str) -> Option<String> {
+        let offset = |offset: isize| match offset {
+            0 => String::new(),
+            _ => format!("{:+} ms", offset),
+        };
+
+        let value = match name {
+            "artist" => state.song.artist.clone(),
+            "title" => state.song.title.clone(),
+            "album" => state.song.album.clone(),
+            "elapsed" => Bars::format_time(state.song.position),
+            "duration" => Bars::format_time(state.song.duration),
+            "progress" => Bars::progress(state.song.position, state.song.duration),
+            "status" => state.song.status.clone(),
+            "source" => state
+                .lyric
+                .source()
+                .rsplit('/')
+                .next()
+                .unwrap_or("")
+                .to_string(),
+            "offset" => offset(state.lyric.offset()),
+            "global_offset" => offset(state.global_offset),
+            _ => return None,
+        };
+
+        Some(value)
+    }
+
+    /// List the keybindings over the lyric.
+    pub fn open_help(&mut self) -> Result<()> {
+        self.help = true;
+        Gui::print_help(&self.keymap.help())?;
+        Ok(())
+    }
+
+    /// Remove the keybindings list, leaving the view as it was.
+    pub fn close_help(&mut self, state: &State) -> Result<()> {
+        self.help = false;
+        Gui::clear_screen()?;
+        self.redraw_lyric(state)?;
+        self.print_bars(state)
+    }
+
+    /// Draw the whole screen again, as when the terminal is resized.
+    pub fn redraw(&mut self, state: &State) -> Result<()> {
+        Gui::clear_screen()?;
+        match &self.screen {
+            Screen::Lyric => self.print_lyric(state, None)?,
+            Screen::LyricMissing { artist, title } => {
+                Gui::print_lyric_not_found_error(artist, title, &Lyric::digest(artist, title))?
+            }
+            Screen::Error(message) => Gui::print_general_error(message)?,
+        }
+        match self.help {
+            true => Gui::print_help(&self.keymap.help())?,
+            false => self.print_bars(state)?,
+        }
+        self.redraw_status = true;
+        Ok(())
+    }
+
+    /// Draw the header and footer with the song as of `state`.
+    fn print_bars(&self, state: &State) -> Result<()> {
+        if !self.showing_lyric() {
+            return Ok(());
+        }
+
+        Gui::print_header(&Bars::render(&self.bars.header, &|name| {
+            Tui::bar_field(state, name)
+        }))?;
+        Gui::print_footer(&Bars::render(&self.bars.footer, &|name| {
+            Tui::bar_field(state, name)
+        }))?;
+        Ok(())
+    }
+
+    /// Work of every tick while the lyric is shown, once the song is updated:
+    /// the work of a frame, and drawing the bars.
+    pub fn tick(&mut self, state: &State) -> Result<()> {
+        self.frame(state)?;
+
+        /* The bars share the screen with the lyric only */
+        if !self.help {
+            self.print_bars(state)?;
+        }
+        Ok(())
+    }
+
+    /// Work of every frame while the lyric is shown: following the song again
+    /// after browsing for a while, scrolling, and drawing the status row.
+    pub fn frame(&mut self, state: &State) -> Result<()> {
+        if !self.showing_lyric() {
+            return Ok(());
+        }
+
+        if let Some((_, moved)) = self.browse
+            && moved.elapsed() >= config::get().browse_timeout
+            && !self.editing()
+            && !self.help
+        {
+            self.follow(state)?;
+        }
+
+        /* Next frame of the scrolling, the last one without transition */
+        if self.transition.is_some() {
+            let transition = self.transition();
+            if !self.help {
+                self.print_lyric(state, transition)?;
+            }
+            if transition.is_none() {
+                self.transition = None;
+            }
+        }
+
+        /* The status row shares the screen with the lyric only */
+        if self.redraw_status && !self.help {
+            Gui::print_status(&self.status_line(state))?;
+            self.redraw_status = false;
+        }
+
+        Ok(())
+    }
+
+    /* Leave browsing, searching and the help for an error screen */
+    fn show(&mut self, screen: Screen) {
+        self.screen = screen;
+        self.transition = None;
+        self.browse = None;
+        self.search = None;
+        self.help = false;
+    }
+}
+
+impl Sink for Tui {
+    fn event(&mut self, event: &Event) -> Result<()> {
+        match event {
+            Event::SongChanged(state) => {
+                self.show(Screen::Lyric);
+                self.message.clear();
+                Gui::clear_screen()?;
+                self.print_lyric(state, None)?;
+                self.redraw_status = true;
+            }
+            Event::VerseChanged { state, previous } => {
+                if !self.animation.is_zero() && self.browse.is_none() {
+                    self.transition = Some((*previous, Instant::now()));
+                }
+                /* The lyric under the help is drawn again when it is closed */
+                if !self.help {
+                    self.print_lyric(state, self.transition())?;
+                }
+                self.redraw_status = true;
+            }
+            /* The bars are drawn on every tick */
+            Event::StateChanged(_) => {}
+            Event::LyricMissing(song) => {
+                self.show(Screen::LyricMissing {
+                    artist: song.artist.clone(),
+                    title: song.title.clone(),
+                });
+                Gui::print_lyric_not_found_error(
+                    &song.artist,
+                    &song.title,
+                    &Lyric::digest(&song.artist, &song.title),
+                )?;
+            }
+            Event::PlayerError(error) => {
+                let message = match error {
+                    PlayerError::PlayerUnreachable => "Can't connect to CMUS socket",
+                    PlayerError::SongUnparsable => "Can't parse playing song",
+                    PlayerError::NoLyricsDir => "No lyrics directory set",
+                };
+                self.show(Screen::Error(message.to_string()));
+                Gui::print_general_error(message)?;
+            }
+        }
+
+        Ok(())
+    }
+}
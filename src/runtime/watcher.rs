@@ -0,0 +1,77 @@
+use std::fs::metadata;
+use std::time::SystemTime;
+
+/// Detects files being created, renamed or removed inside a directory.
+///
+/// Any of these operations updates the modification time of the directory
+/// itself, so polling it once per runtime task is enough to notice a new
+/// lyric file without reading the directory content. Watching a file the
+/// same way notices it being created or written.
+pub struct DirWatcher {
+    path: String,
+    modified: Option<SystemTime>,
+}
+
+impl DirWatcher {
+    pub fn new(path: String) -> DirWatcher {
+        let modified = DirWatcher::modified_time(&path);
+        DirWatcher { path, modified }
+    }
+
+    fn modified_time(path: &str) -> Option<SystemTime> {
+        metadata(path).and_then(|meta| meta.modified()).ok()
+    }
+
+    pub fn changed(&mut self) -> bool {
+        let modified = DirWatcher::modified_time(&self.path);
+        if modified == self.modified {
+            return false;
+        }
+
+        self.modified = modified;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use crate::runtime::watcher::DirWatcher;
+    use std::fs;
+
+    #[test]
+    fn new_file_is_detected() {
+        let dir = std::env::temp_dir().join(format!("ciryl-watcher-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        let mut watcher = DirWatcher::new(dir.to_string_lossy().to_string());
+        assert!(!watcher.changed());
+
+        /* Make sure the new modification time differs from the first one */
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(dir.join("lyric.lrc"), "[00:01.00] verse").unwrap();
+        assert!(watcher.changed());
+        assert!(!watcher.changed());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn written_file_is_detected() {
+        let dir = std::env::temp_dir().join(format!("ciryl-watcher-file-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("lyric.lrc");
+
+        let mut watcher = DirWatcher::new(file.to_string_lossy().to_string());
+        assert!(!watcher.changed());
+
+        /* Created empty, as a copy does, then written */
+        fs::write(&file, "").unwrap();
+        assert!(watcher.changed());
+        std::thread::sleep(std::time::Duration::from_millis(20));
+        fs::write(&file, "[00:01.00] verse").unwrap();
+        assert!(watcher.changed());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,36 @@
+use crate::runtime::error::RuntimeError;
+use crate::runtime::{self, StreamFormat, StreamRuntime};
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+const STREAM_USAGE: &str = "Usage: ciryl stream [OPTIONS]
+
+Write a line to stdout each time the verse sung on cmus changes, for
+status bars such as waybar, polybar, i3blocks or tmux. The line is
+empty while there is no lyric.
+
+OPTIONS
+  -j, --json          Write JSON objects with artist, title, album,
+                      status, lyric, verse, next, position, duration
+                      and progress fields.
+  -h, --help          Print this message.";
+
+pub fn stream(args: &[String]) -> Result<()> {
+    let format = match args {
+        [] => StreamFormat::Plain,
+        [json] if json == "-j" || json == "--json" => StreamFormat::Json,
+        [help] if help == "-h" || help == "--help" => {
+            println!("{}", STREAM_USAGE);
+            return Ok(());
+        }
+        [arg, ..] => {
+            return Err(RuntimeError::UsageError(format!(
+                "unexpected argument '{}'",
+                arg
+            )));
+        }
+    };
+
+    let mut rt = StreamRuntime::new(format)?;
+    runtime::run(|| rt.task())
+}
@@ -0,0 +1,36 @@
+use crate::runtime::error::RuntimeError;
+use crate::runtime::{self, SyncRuntime};
+
+type Result<T> = std::result::Result<T, RuntimeError>;
+
+const SYNC_USAGE: &str = "Usage: ciryl sync LYRIC_FILE
+
+Stamp each line of LYRIC_FILE with the position of the song playing on
+cmus and save the synchronized lyric in $LYRICS_DIR.
+
+KEYS
+  space       Stamp the current line and move to the next one.
+  +, -        Nudge the current or last stamped line by 10 ms.
+  u           Undo the last change.
+  j, k        Move to the next or previous line.
+  w           Save the lyric.
+  q           Quit.";
+
+pub fn sync(args: &[String]) -> Result<()> {
+    let file = match args {
+        [help] if help == "-h" || help == "--help" => {
+            println!("{}", SYNC_USAGE);
+            return Ok(());
+        }
+        [file] => file,
+        _ => {
+            return Err(RuntimeError::UsageError(
+                "exactly one LYRIC_FILE argument is required".to_string(),
+            ));
+        }
+    };
+
+    let text = std::fs::read_to_string(file)?;
+    let mut rt = SyncRuntime::new(&text)?;
+    runtime::run(|| rt.task())
+}